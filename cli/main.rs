@@ -1,35 +1,185 @@
-//! Command line interface. Takes argument of a filepath to source code.
-use runtime::interpreter;
-use std::env::args;
+//! Command line interface. Takes flags for the interpreter, then a filepath to source code
+//! followed by the arguments for the script. Without a filepath it starts a REPL instead.
+use runtime::{error::Error, interpreter::Interpreter};
+use std::{
+	env::args,
+	io::{stdin, stdout, Write},
+	process,
+};
 
-fn main() -> Result<(), String> {
-	const HELP: &str =
-		"Arg 1\tProvide a file path to run\nArg 2\t--bench\tprints the time taken after running";
+const HELP: &str = "Usage: simple [flags] [file path] [script args]
+Runs the file, or starts a REPL without one. The script gets the args after the path from args()
 
-	let filepath = match args().nth(1) {
-		Some(v) => v,
-		None => return Err("No argument of <filepath/> provided to application".to_string()),
+Flags:
+\t--bench\tprints the time taken after running
+\t--seed n, --seed=n\tseeds the random builtins with n, so runs can be replayed
+\t--allow-read=DIR\tlets the script read files in DIR, or the working directory without =DIR
+\t--allow-write=DIR\tlets the script write files in DIR, or the working directory without =DIR
+\t--allow-env\tlets the script read environment variables with env(name)
+
+Exits with the code given to exit(code), 70 if there's an uncaught error or 2 for bad flags";
+
+/// Exit code for errors the script didn't catch, including ones before it runs. It's EX_SOFTWARE
+/// from sysexits.h, so scripts can still use 1 for their own failures
+const ERROR: i32 = 70;
+/// Exit code for flags that can't be used
+const USAGE: i32 = 2;
+
+#[derive(Default)]
+struct Options {
+	bench: bool,
+	seed: Option<u64>,
+	read: Vec<String>,
+	write: Vec<String>,
+	allow_env: bool,
+}
+
+impl Options {
+	fn apply(&self, interpreter: &mut Interpreter) -> Result<(), String> {
+		if let Some(seed) = self.seed {
+			interpreter.seed(seed);
+		}
+		for dir in &self.read {
+			interpreter.fs.allow_read(dir)?;
+		}
+		for dir in &self.write {
+			interpreter.fs.allow_write(dir)?;
+		}
+		interpreter.allow_env = self.allow_env;
+		Ok(())
+	}
+}
+
+fn main() {
+	let mut args = args().skip(1).peekable();
+
+	let mut options = Options::default();
+	while let Some(flag) = args.next_if(|v| v.starts_with("--")) {
+		if let Err(e) = parse_flag(&mut options, &flag, &mut args) {
+			eprintln!("{e}, run `simple help` to see the flags");
+			process::exit(USAGE);
+		}
+	}
+
+	let filepath = args.next();
+	if filepath.as_deref() == Some("help") && std::fs::metadata("help").is_err() {
+		println!("{HELP}");
+		return;
+	}
+
+	let res = match filepath {
+		Some(path) => run(&path, args.collect(), &options),
+		None => repl(&options),
 	};
 
-	let source_text = match std::fs::read_to_string(filepath.clone()) {
-		Ok(v) => v,
+	match res {
+		Ok(()) => {}
+		Err(Error::Exit(code)) => process::exit(code),
 		Err(e) => {
-			if filepath.eq("help") {
-				println!("{HELP}");
-				return Ok(());
-			} else {
-				return Err(e.to_string());
-			}
+			eprintln!("{e}");
+			process::exit(ERROR);
 		}
+	}
+}
+
+/// Sets the option for `flag`, taking its value from after `=` or else from `rest` if it
+/// needs one
+fn parse_flag(
+	options: &mut Options,
+	flag: &str,
+	rest: &mut impl Iterator<Item = String>,
+) -> Result<(), String> {
+	let (flag, value) = match flag.split_once('=') {
+		Some((flag, value)) => (flag, Some(value.to_string())),
+		None => (flag, None),
 	};
 
-	let now = std::time::Instant::now();
+	match flag {
+		"--seed" => {
+			let value = value
+				.or_else(|| rest.next())
+				.ok_or("--seed needs a number after it")?;
+			let value = value
+				.parse::<i64>()
+				.map_err(|_| format!("Invalid seed {value}"))?;
+			options.seed = Some(value as u64);
+		}
+		"--allow-read" => options.read.push(value.unwrap_or(".".to_string())),
+		"--allow-write" => options.write.push(value.unwrap_or(".".to_string())),
+		"--bench" | "--allow-env" if value.is_some() => {
+			return Err(format!("{flag} doesn't take a value"))
+		}
+		"--bench" => options.bench = true,
+		"--allow-env" => options.allow_env = true,
+		_ => return Err(format!("Unknown flag {flag}")),
+	}
+	Ok(())
+}
+
+fn run(path: &str, script_args: Vec<String>, options: &Options) -> Result<(), Error> {
+	let mut interpreter = Interpreter::from_file(path)?;
+	options.apply(&mut interpreter)?;
+	interpreter.args = script_args;
+
+	let start = interpreter.clock.monotonic();
 
-	interpreter::run_source(source_text.as_str())?;
+	interpreter.run()?;
 
-	if args().nth(2) == Some(String::from("--bench")) {
-		println!("\n{}s", now.elapsed().as_secs_f64());
+	if options.bench {
+		println!("\n{}s", interpreter.clock.monotonic() - start);
 	}
 
 	Ok(())
 }
+
+/// Reads and runs input line by line, printing the repr of each expression's value.
+/// Lines are collected until their braces are balanced so blocks can span lines.
+fn repl(options: &Options) -> Result<(), Error> {
+	let mut interpreter = Interpreter::default();
+	options.apply(&mut interpreter)?;
+	let mut source = String::new();
+
+	loop {
+		print!("{}", if source.is_empty() { "> " } else { "... " });
+		stdout().flush().map_err(|e| e.to_string())?;
+
+		let mut line = String::new();
+		if stdin().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+			return Ok(());
+		}
+		source.push_str(&line);
+
+		if open_braces(&source) > 0 {
+			continue;
+		}
+
+		match interpreter.eval(&source) {
+			Ok(Some(v)) => println!("{v}"),
+			Ok(None) => {}
+			Err(e @ Error::Exit(_)) => return Err(e),
+			Err(e) => eprintln!("{e}"),
+		}
+		source.clear();
+	}
+}
+
+/// How many more `{` there are than `}`, not counting the ones in strings or comments
+fn open_braces(source: &str) -> isize {
+	let mut depth = 0;
+	let mut in_string = false;
+	let mut in_comment = false;
+
+	for c in source.chars() {
+		match c {
+			'\n' => in_comment = false,
+			_ if in_comment => {}
+			'"' => in_string = !in_string,
+			_ if in_string => {}
+			'#' => in_comment = true,
+			'{' => depth += 1,
+			'}' => depth -= 1,
+			_ => {}
+		}
+	}
+	depth
+}
@@ -1,6 +1,7 @@
 //!Abstract Syntax Tree
 
 use crate::token::{Token, TokenKind};
+use std::fmt::{self, Display, Formatter};
 
 /// (statement, associated token)
 pub type AstNode = (Statement, Token);
@@ -12,27 +13,181 @@ pub enum Statement {
 	Expression(Expression),
 	FunctionDeclaration {
 		name: String,
-		params: Vec<String>,
-		body: Vec<Statement>,
+		params: Vec<Param>,
+		return_type: Option<Type>,
+		body: Vec<AstNode>,
+	},
+	StructDeclaration {
+		name: String,
+		fields: Vec<String>,
+	},
+	/// Adds methods, which are `FunctionDeclaration`s, to the struct called `name`
+	Impl {
+		name: String,
+		methods: Vec<Statement>,
 	},
 	VarDeclaration {
 		name: String,
+		annotation: Option<Type>,
+		value: Expression,
+	},
+	/// Like a var, but it can't be assigned to after
+	ConstDeclaration {
+		name: String,
+		annotation: Option<Type>,
 		value: Expression,
 	},
 	While {
 		condition: Expression,
-		body: Vec<Statement>,
+		body: Vec<AstNode>,
+	},
+	For {
+		variable: String,
+		iterable: Expression,
+		body: Vec<AstNode>,
+	},
+	Block(Vec<AstNode>),
+	/// `import "path" as name`
+	Import {
+		path: String,
+		name: String,
+	},
+	/// `from "path" import a, b`
+	FromImport {
+		path: String,
+		names: Vec<String>,
+	},
+	Throw(Expression),
+	/// `try { } catch name { } finally { }` where either catch or finally can be left out
+	Try {
+		body: Vec<AstNode>,
+		catch: Option<(String, Vec<AstNode>)>,
+		finally: Option<Vec<AstNode>>,
 	},
-	Block(Vec<Statement>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
 	Bool(bool),
-	Number(f64),
+	Nil,
+	Int(i64),
+	Float(f64),
 	Str(String),
 	Identifier(String),
-	Assign(Box<Expression>, Box<Expression>),
-	Call { name: String, args: Vec<Expression> },
-	BinOp(Box<Expression>, TokenKind, Box<Expression>), // left, op, right
+	List(Vec<Expression>),
+	/// `target = value` and the `=` token
+	Assign(Box<Expression>, Token, Box<Expression>),
+	/// `target op= value`, where the op token has the kind of the op without the `=`
+	CompoundAssign(Box<Expression>, Token, Box<Expression>),
+	Call {
+		name: String,
+		/// The name's token, which errors about the call are positioned at
+		token: Token,
+		args: Vec<Expression>,
+		/// `name: value` arguments, which come after the positional ones
+		named: Vec<(String, Expression)>,
+	},
+	/// A call of any other expression, like `fns[0](1)` or `make()(1)`
+	CallValue {
+		callee: Box<Expression>,
+		args: Vec<Expression>,
+		named: Vec<(String, Expression)>,
+	},
+	/// `Name { field: value, ... }`
+	StructLiteral {
+		name: String,
+		fields: Vec<(String, Expression)>,
+	},
+	/// `object.field`, or `object?.field` when optional
+	Get {
+		object: Box<Expression>,
+		field: String,
+		optional: bool,
+	},
+	/// `object.method(args)`, or `object?.method(args)` when optional
+	MethodCall {
+		object: Box<Expression>,
+		method: String,
+		args: Vec<Expression>,
+		named: Vec<(String, Expression)>,
+		optional: bool,
+	},
+	/// `value?`, which unwraps an ok result or returns an err result from the function
+	Try(Box<Expression>),
+	/// `left ?? right`, where right is only evaluated if left is nil
+	Coalesce(Box<Expression>, Box<Expression>),
+	Index(Box<Expression>, Box<Expression>), // target, index
+	Slice {
+		target: Box<Expression>,
+		start: Option<Box<Expression>>,
+		end: Option<Box<Expression>>,
+	},
+	Unary(Token, Box<Expression>),
+	BinOp(Box<Expression>, Token, Box<Expression>), // left, op, right
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+	pub name: String,
+	pub annotation: Option<Type>,
+	/// Evaluated when the argument isn't given, after the parameters before it are set
+	pub default: Option<Expression>,
+	/// `...name` collects the remaining positional arguments into a list
+	pub rest: bool,
+}
+
+/// A type annotation like `x: number`. They're only used by the checker before running
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+	/// Anything, which is the type of values that aren't known before running
+	Any,
+	Nil,
+	Bool,
+	Int,
+	Float,
+	/// An int or a float
+	Number,
+	Str,
+	List,
+	Map,
+	Function,
+	/// An instance of the struct with this name
+	Instance(String),
+}
+
+impl Type {
+	pub fn from_name(name: &str) -> Type {
+		match name {
+			"any" => Type::Any,
+			"nil" => Type::Nil,
+			"bool" => Type::Bool,
+			"int" => Type::Int,
+			"float" => Type::Float,
+			"number" => Type::Number,
+			"string" => Type::Str,
+			"list" => Type::List,
+			"map" => Type::Map,
+			"function" => Type::Function,
+			_ => Type::Instance(name.to_string()),
+		}
+	}
+}
+
+impl Display for Type {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		let name = match self {
+			Type::Any => "any",
+			Type::Nil => "nil",
+			Type::Bool => "bool",
+			Type::Int => "int",
+			Type::Float => "float",
+			Type::Number => "number",
+			Type::Str => "string",
+			Type::List => "list",
+			Type::Map => "map",
+			Type::Function => "function",
+			Type::Instance(name) => name,
+		};
+		write!(f, "{name}")
+	}
 }
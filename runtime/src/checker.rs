@@ -0,0 +1,676 @@
+//! Checks type annotations before running, along with operations that would fail on the
+//! types that are known. Unannotated variables get the type of the value they're declared
+//! with, until something of another type is assigned to them, and loops are checked with
+//! what any of their iterations could have assigned. Everything else, like the results of
+//! builtins, is `any`, which is compatible with every type.
+//!
+//! Only mismatches that an annotation is part of are reported. The rest are left to fail
+//! while running, so unannotated code runs the same as without the checker and its errors
+//! can still be caught.
+
+use crate::{ast::*, token::Token, token::TokenKind as tk};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+enum Binding {
+	Var {
+		ty: Type,
+		annotated: bool,
+	},
+	Function {
+		params: Vec<Param>,
+		return_type: Option<Type>,
+	},
+	Struct,
+}
+
+struct Checker {
+	scopes: Vec<HashMap<String, Binding>>,
+	/// The first scope of the function being checked. Functions can be called from anywhere,
+	/// so unannotated variables from outside of them could have any type
+	function_scope: usize,
+	/// The name and return type of the function being checked, if it has one
+	returns: Option<(String, Type)>,
+	/// Whether a loop is being checked, which is checked again until its types settle
+	looping: bool,
+	/// The statement or expression being checked, which errors are positioned at
+	curtok: Token,
+	errors: Vec<String>,
+}
+
+/// A type, and whether an annotation says so rather than it only being inferred
+#[derive(Debug, Clone)]
+struct Typed {
+	ty: Type,
+	declared: bool,
+}
+
+impl Typed {
+	fn inferred(ty: Type) -> Typed {
+		Typed {
+			ty: ty,
+			declared: false,
+		}
+	}
+}
+
+/// Returns every mismatch found, one per line
+pub fn check(program: &[AstNode]) -> Result<(), String> {
+	let mut checker = Checker {
+		scopes: vec![HashMap::new()],
+		function_scope: 0,
+		returns: None,
+		looping: false,
+		curtok: match program.first() {
+			Some((_, token)) => token.clone(),
+			None => return Ok(()),
+		},
+		errors: vec![],
+	};
+
+	checker.statements(program);
+
+	match checker.errors.is_empty() {
+		true => Ok(()),
+		false => Err(checker.errors.join("\n")),
+	}
+}
+
+/// Whether a value of type `actual` could be used where `expected` is annotated
+fn compatible(expected: &Type, actual: &Type) -> bool {
+	use Type::*;
+	match (expected, actual) {
+		(Any, _) | (_, Any) => true,
+		(Number, Int | Float) | (Int | Float, Number) => true,
+		_ => expected == actual,
+	}
+}
+
+fn is_number(ty: &Type) -> bool {
+	matches!(ty, Type::Int | Type::Float | Type::Number)
+}
+
+impl Checker {
+	fn statements(&mut self, statements: &[AstNode]) {
+		for (statement, token) in statements {
+			self.curtok = token.clone();
+			self.statement(statement);
+		}
+	}
+
+	/// Checks statements in a new scope that starts with `bindings`
+	fn block(&mut self, bindings: Vec<(String, Binding)>, body: &[AstNode]) {
+		self.scopes.push(bindings.into_iter().collect());
+		self.statements(body);
+		self.scopes.pop();
+	}
+
+	fn statement(&mut self, statement: &Statement) {
+		match statement {
+			Statement::Expression(expr) | Statement::Throw(expr) => {
+				self.expression(expr);
+			}
+			Statement::Return(expr) => {
+				let actual = self.expression(expr).ty;
+				if let Some((name, expected)) = &self.returns {
+					if !compatible(expected, &actual) {
+						let message =
+							format!("`{name}` should return {expected}, instead got {actual}");
+						self.error(message);
+					}
+				}
+			}
+			Statement::VarDeclaration {
+				name,
+				annotation,
+				value,
+			}
+			| Statement::ConstDeclaration {
+				name,
+				annotation,
+				value,
+			} => {
+				let actual = self.expression(value).ty;
+				let binding = match annotation {
+					Some(ty) => {
+						if !compatible(ty, &actual) {
+							self.error(format!("`{name}` should be {ty}, instead got {actual}"));
+						}
+						Binding::Var {
+							ty: ty.clone(),
+							annotated: true,
+						}
+					}
+					None => Binding::Var {
+						ty: actual,
+						annotated: false,
+					},
+				};
+				self.bind(name, binding);
+			}
+			Statement::FunctionDeclaration {
+				name,
+				params,
+				return_type,
+				body,
+			} => {
+				self.bind(
+					name,
+					Binding::Function {
+						params: params.clone(),
+						return_type: return_type.clone(),
+					},
+				);
+				self.function(name, params, return_type, body);
+			}
+			Statement::StructDeclaration { name, .. } => self.bind(name, Binding::Struct),
+			Statement::Impl { methods, .. } => {
+				for method in methods {
+					if let Statement::FunctionDeclaration {
+						name,
+						params,
+						return_type,
+						body,
+					} = method
+					{
+						self.function(name, params, return_type, body);
+					}
+				}
+			}
+			Statement::Import { name, .. } => self.bind(name, Binding::any()),
+			Statement::FromImport { names, .. } => {
+				for name in names {
+					self.bind(name, Binding::any());
+				}
+			}
+			Statement::While { condition, body } => self.repeated(&|checker: &mut Checker| {
+				let condition = checker.expression(condition);
+				if condition.declared && !compatible(&Type::Bool, &condition.ty) {
+					checker.error(format!(
+						"Expression after while should be bool, instead got {}",
+						condition.ty
+					));
+				}
+				checker.block(vec![], body);
+			}),
+			Statement::For {
+				variable,
+				iterable,
+				body,
+			} => {
+				let item = match self.expression(iterable).ty {
+					Type::Str => Type::Str,
+					_ => match iterable {
+						Expression::Call { name, .. }
+							if name == "range" && self.lookup(name).is_none() =>
+						{
+							Type::Int
+						}
+						_ => Type::Any,
+					},
+				};
+				self.repeated(&|checker: &mut Checker| {
+					checker.block(vec![(variable.clone(), Binding::var(item.clone()))], body)
+				});
+			}
+			Statement::Block(body) => self.block(vec![], body),
+			Statement::Try {
+				body,
+				catch,
+				finally,
+			} => {
+				self.block(vec![], body);
+				if let Some((name, handler)) = catch {
+					self.block(vec![(name.clone(), Binding::any())], handler);
+				}
+				if let Some(finally) = finally {
+					self.block(vec![], finally);
+				}
+			}
+		}
+	}
+
+	/// Checks a loop with `check`. Later iterations see what earlier ones assigned, so it's
+	/// checked until the types of the variables stop changing and only the last check
+	/// reports errors. Loops inside it are settled along with it
+	fn repeated(&mut self, check: &dyn Fn(&mut Checker)) {
+		if self.looping {
+			check(self);
+			return;
+		}
+
+		let token = self.curtok.clone();
+		self.looping = true;
+		loop {
+			let before = self.var_types();
+			let errors = self.errors.len();
+			check(self);
+			self.errors.truncate(errors);
+			self.curtok = token.clone();
+			if self.var_types() == before {
+				break;
+			}
+		}
+		check(self);
+		self.looping = false;
+	}
+
+	/// The type of every variable in scope, innermost scope last
+	fn var_types(&self) -> Vec<HashMap<String, Type>> {
+		self.scopes
+			.iter()
+			.map(|scope| {
+				scope
+					.iter()
+					.filter_map(|(name, binding)| match binding {
+						Binding::Var { ty, .. } => Some((name.clone(), ty.clone())),
+						_ => None,
+					})
+					.collect()
+			})
+			.collect()
+	}
+
+	fn function(
+		&mut self,
+		name: &String,
+		params: &[Param],
+		return_type: &Option<Type>,
+		body: &[AstNode],
+	) {
+		let mut bindings = vec![];
+		for param in params {
+			// defaults are evaluated where the earlier parameters are set
+			if let Some(default) = &param.default {
+				self.scopes.push(bindings.iter().cloned().collect());
+				let actual = self.expression(default).ty;
+				self.scopes.pop();
+				if let Some(ty) = &param.annotation {
+					if !compatible(ty, &actual) {
+						self.error(format!(
+							"Default of parameter `{}` should be {ty}, instead got {actual}",
+							param.name
+						));
+					}
+				}
+			}
+
+			// the annotation of a rest parameter is for each argument it collects
+			let binding = match param.rest {
+				true => Binding::Var {
+					ty: Type::List,
+					annotated: true,
+				},
+				false => Binding::Var {
+					ty: param.annotation.clone().unwrap_or(Type::Any),
+					annotated: param.annotation.is_some(),
+				},
+			};
+			bindings.push((param.name.clone(), binding));
+		}
+
+		let outer_scope = std::mem::replace(&mut self.function_scope, self.scopes.len());
+		let outer_returns = std::mem::replace(
+			&mut self.returns,
+			return_type.clone().map(|v| (name.clone(), v)),
+		);
+		let outer_tok = self.curtok.clone();
+
+		self.block(bindings, body);
+
+		self.function_scope = outer_scope;
+		self.returns = outer_returns;
+		self.curtok = outer_tok;
+	}
+
+	fn expression(&mut self, expr: &Expression) -> Typed {
+		let ty = match expr {
+			Expression::Bool(_) => Type::Bool,
+			Expression::Nil => Type::Nil,
+			Expression::Int(_) => Type::Int,
+			Expression::Float(_) => Type::Float,
+			Expression::Str(_) => Type::Str,
+			Expression::Identifier(name) => match self.lookup(name) {
+				Some(Binding::Var { ty, annotated }) => {
+					return Typed {
+						ty: ty,
+						declared: annotated,
+					}
+				}
+				Some(Binding::Function { .. }) => Type::Function,
+				_ => Type::Any,
+			},
+			Expression::List(items) => {
+				for item in items {
+					self.expression(item);
+				}
+				Type::List
+			}
+			Expression::Assign(target, token, value) => {
+				let actual = self.expression(value).ty;
+				self.at(token, |checker| checker.assign(target, actual));
+				Type::Nil
+			}
+			Expression::CompoundAssign(target, op, value) => {
+				let current = self.expression(target);
+				let value = self.expression(value);
+				self.at(op, |checker| {
+					let actual = checker.binary_op(&current, &op.kind, &value);
+					checker.assign(target, actual.ty);
+				});
+				Type::Nil
+			}
+			Expression::Call {
+				name,
+				token,
+				args,
+				named,
+			} => {
+				let args: Vec<Type> = args.iter().map(|v| self.expression(v).ty).collect();
+				let named: Vec<(&String, Type)> = named
+					.iter()
+					.map(|(k, v)| (k, self.expression(v).ty))
+					.collect();
+				return self.at(token, |checker| checker.call(name, &args, &named));
+			}
+			Expression::StructLiteral { name, fields } => {
+				for (_, value) in fields {
+					self.expression(value);
+				}
+				Type::Instance(name.clone())
+			}
+			Expression::Get { object, .. } => {
+				self.expression(object);
+				Type::Any
+			}
+			Expression::MethodCall {
+				object: callee,
+				args,
+				named,
+				..
+			}
+			| Expression::CallValue {
+				callee,
+				args,
+				named,
+			} => {
+				self.expression(callee);
+				for arg in args.iter().chain(named.iter().map(|(_, v)| v)) {
+					self.expression(arg);
+				}
+				Type::Any
+			}
+			Expression::Try(expr) => {
+				self.expression(expr);
+				Type::Any
+			}
+			Expression::Coalesce(left, right) => {
+				self.expression(left);
+				self.expression(right);
+				Type::Any
+			}
+			Expression::Index(target, index) => {
+				let target = self.expression(target);
+				self.expression(index);
+				match target.ty {
+					Type::Str => return target,
+					_ => Type::Any,
+				}
+			}
+			Expression::Slice { target, start, end } => {
+				let target = self.expression(target);
+				for bound in [start, end].into_iter().flatten() {
+					self.expression(bound);
+				}
+				match target.ty {
+					Type::Str | Type::List => return target,
+					_ => Type::Any,
+				}
+			}
+			Expression::Unary(op, expr) => {
+				let operand = self.expression(expr);
+				match (&op.kind, &operand.ty) {
+					(tk::Minus, Type::Int | Type::Float | Type::Number | Type::Any) => {
+						return operand
+					}
+					(tk::Minus, ty) if operand.declared => {
+						let message = format!("Unsupported operation {:?} on {ty}", op.kind);
+						self.at(op, |checker| checker.error(message));
+						Type::Any
+					}
+					_ => Type::Any,
+				}
+			}
+			Expression::BinOp(left, op, right) => {
+				let left = self.expression(left);
+				let right = self.expression(right);
+				return self.at(op, |checker| checker.binary_op(&left, &op.kind, &right));
+			}
+		};
+		Typed::inferred(ty)
+	}
+
+	/// Runs `f` with errors positioned at `token`, for the expressions that have one
+	fn at<T>(&mut self, token: &Token, f: impl FnOnce(&mut Checker) -> T) -> T {
+		let outer = std::mem::replace(&mut self.curtok, token.clone());
+		let res = f(self);
+		self.curtok = outer;
+		res
+	}
+
+	/// The type of `left op right`, reporting operations that are never supported for the types
+	/// when either of them is declared
+	fn binary_op(&mut self, left: &Typed, op: &tk, right: &Typed) -> Typed {
+		let declared = left.declared || right.declared;
+		let ty = match self.operation(&left.ty, op, &right.ty) {
+			Some(ty) => ty,
+			None if matches!(
+				op,
+				tk::LessThan | tk::GreaterThan | tk::LessEquals | tk::GreaterEquals
+			) =>
+			{
+				if declared {
+					self.error(format!("Can't compare {} with {}", left.ty, right.ty));
+				}
+				Type::Bool
+			}
+			None => {
+				if declared {
+					self.error(format!(
+						"Unsupported operation {op:?} between {} and {}",
+						left.ty, right.ty
+					));
+				}
+				Type::Any
+			}
+		};
+		Typed {
+			ty: ty,
+			declared: declared,
+		}
+	}
+
+	/// The type of `left op right`, or `None` if it's never supported for the types
+	fn operation(&self, left: &Type, op: &tk, right: &Type) -> Option<Type> {
+		use Type::*;
+		let comparison = matches!(
+			op,
+			tk::LessThan | tk::GreaterThan | tk::LessEquals | tk::GreaterEquals
+		);
+		let arithmetic = matches!(
+			op,
+			tk::Plus | tk::Minus | tk::Star | tk::Slash | tk::Percent | tk::StarStar
+		);
+
+		match (left, right) {
+			_ if matches!(op, tk::Equals | tk::NotEquals) => Some(Bool),
+			(Any, _) | (_, Any) if comparison => Some(Bool),
+			(Any, _) | (_, Any) => Some(Any),
+			_ if comparison => {
+				let ordered = (is_number(left) && is_number(right))
+					|| (left == right && matches!(left, Str | List));
+				ordered.then_some(Bool)
+			}
+			(Int, Int) if *op == tk::StarStar => Some(Number),
+			(Int, Int) => Some(Int),
+			(Float, _) | (_, Float) if is_number(left) && is_number(right) && arithmetic => {
+				Some(Float)
+			}
+			_ if is_number(left) && is_number(right) && arithmetic => Some(Number),
+			// bitwise operations on a number that might be an int
+			(Number, Int | Number) | (Int, Number) => Some(Any),
+			(Str, Str) if *op == tk::Plus => Some(Str),
+			(Bool, Bool) if matches!(op, tk::Ampersand | tk::Pipe | tk::Caret) => Some(Bool),
+			_ => None,
+		}
+	}
+
+	/// The type a call returns, which is declared if the function annotates it
+	fn call(&mut self, name: &String, args: &[Type], named: &[(&String, Type)]) -> Typed {
+		match self.lookup(name) {
+			Some(Binding::Function {
+				params,
+				return_type,
+			}) => {
+				self.arguments(name, &params, args, named);
+				match return_type {
+					Some(ty) => Typed {
+						ty: ty,
+						declared: true,
+					},
+					None => Typed::inferred(Type::Any),
+				}
+			}
+			Some(Binding::Struct) => Typed::inferred(Type::Instance(name.clone())),
+			Some(Binding::Var {
+				ty,
+				annotated: true,
+			}) if !compatible(&Type::Function, &ty) => {
+				self.error(format!("`{name}` is {ty}, not a function"));
+				Typed::inferred(Type::Any)
+			}
+			_ => Typed::inferred(Type::Any),
+		}
+	}
+
+	/// Checks the arguments given to annotated parameters. How many arguments there are is
+	/// left to the call, which reports it while running
+	fn arguments(
+		&mut self,
+		name: &String,
+		params: &[Param],
+		args: &[Type],
+		named: &[(&String, Type)],
+	) {
+		let positional: Vec<&Param> = params.iter().filter(|v| !v.rest).collect();
+		let rest = params.iter().find(|v| v.rest);
+
+		for (i, arg) in args.iter().enumerate() {
+			let param = match positional.get(i) {
+				Some(param) => *param,
+				None => match rest {
+					Some(param) => param,
+					None => break,
+				},
+			};
+			if let Some(ty) = &param.annotation {
+				if !compatible(ty, arg) {
+					self.error(format!(
+						"Argument {} of `{name}` should be {ty}, instead got {arg}",
+						i + 1
+					));
+				}
+			}
+		}
+
+		for (arg, actual) in named {
+			let param = positional.iter().find(|v| v.name == **arg);
+			if let Some(ty) = param.and_then(|v| v.annotation.as_ref()) {
+				if !compatible(ty, actual) {
+					self.error(format!(
+						"Argument `{arg}` of `{name}` should be {ty}, instead got {actual}"
+					));
+				}
+			}
+		}
+	}
+
+	fn assign(&mut self, target: &Expression, actual: Type) {
+		let name = match target {
+			Expression::Identifier(name) => name,
+			_ => {
+				self.expression(target);
+				return;
+			}
+		};
+
+		let (index, binding) = match self.find(name) {
+			Some(v) => v,
+			None => return,
+		};
+
+		match binding {
+			Binding::Var {
+				ty,
+				annotated: true,
+			} => {
+				if !compatible(&ty, &actual) {
+					self.error(format!("`{name}` should be {ty}, instead got {actual}"));
+				}
+			}
+			// unannotated variables can change type, after which it's unknown
+			_ => {
+				let unchanged = matches!(&binding, Binding::Var { ty, .. } if *ty == actual);
+				if !unchanged {
+					self.scopes[index].insert(name.clone(), Binding::any());
+				}
+			}
+		}
+	}
+
+	fn bind(&mut self, name: &String, binding: Binding) {
+		self.scopes
+			.last_mut()
+			.unwrap()
+			.insert(name.clone(), binding);
+	}
+
+	/// Finds the scope a name is declared in and what it's bound to
+	fn find(&self, name: &String) -> Option<(usize, Binding)> {
+		self.scopes
+			.iter()
+			.enumerate()
+			.rev()
+			.find_map(|(i, v)| v.get(name).map(|v| (i, v.clone())))
+	}
+
+	fn lookup(&self, name: &String) -> Option<Binding> {
+		let (index, binding) = self.find(name)?;
+		match binding {
+			Binding::Var {
+				annotated: false, ..
+			} if index < self.function_scope => Some(Binding::any()),
+			binding => Some(binding),
+		}
+	}
+
+	fn error(&mut self, text: String) {
+		self.errors.push(format!(
+			"(Ln {}, Col {}) {}",
+			self.curtok.ln, self.curtok.col, text
+		));
+	}
+}
+
+impl Binding {
+	fn var(ty: Type) -> Binding {
+		Binding::Var {
+			ty: ty,
+			annotated: false,
+		}
+	}
+
+	fn any() -> Binding {
+		Binding::var(Type::Any)
+	}
+}
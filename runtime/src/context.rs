@@ -1,25 +1,45 @@
 //! The Context is used to simulate variable scope.
 //! The implementation is stack based and follows similar rules to javascript.
+//!
+//! The first scope holds the builtins and the second the globals of a module. Scopes are
+//! shared, so that every module sees the same builtins and functions can run with the
+//! globals of the module they were declared in.
 
-use crate::object::Object;
-use std::{collections::HashMap, vec};
+use crate::object::{Object, Scope, Variables};
+use std::{cell::RefCell, rc::Rc, vec};
 
 #[derive(Debug, Clone)]
 pub struct Context {
-	stack: Vec<HashMap<String, Object>>,
+	stack: Vec<Scope>,
 }
 
 impl Context {
-	pub fn from(globals: HashMap<String, Object>) -> Context {
+	pub fn from(builtins: Variables) -> Context {
 		Context {
-			stack: vec![globals],
+			stack: vec![Rc::new(RefCell::new(builtins)), Scope::default()],
 		}
 	}
+
+	/// A context with the same builtins that uses `globals` as its global scope
+	pub fn with_globals(&self, globals: Scope) -> Context {
+		Context {
+			stack: vec![self.stack[0].clone(), globals],
+		}
+	}
+
+	pub fn builtins(&self) -> &Scope {
+		&self.stack[0]
+	}
+
+	pub fn globals(&self) -> &Scope {
+		&self.stack[1]
+	}
+
 	/// returns the value starting from the innermost scope
-	pub fn get(&self, k: &String) -> Option<&Object> {
+	pub fn get(&self, k: &String) -> Option<Object> {
 		for curmap in self.stack.iter().rev() {
-			if let Some(v) = curmap.get(k) {
-				return Some(v);
+			if let Some(v) = curmap.borrow().values.get(k) {
+				return Some(v.clone());
 			}
 		}
 		return None;
@@ -27,7 +47,7 @@ impl Context {
 
 	pub fn contains(&self, k: &String) -> Option<usize> {
 		for (i, curmap) in self.stack.iter().rev().enumerate() {
-			if curmap.contains_key(k) {
+			if curmap.borrow().values.contains_key(k) {
 				return Some(self.stack.len() - 1 - i);
 			}
 		}
@@ -35,25 +55,50 @@ impl Context {
 	}
 
 	pub fn insert_at(&mut self, k: String, v: Object, scope_index: usize) {
-		self.stack[scope_index].insert(k, v);
+		self.stack[scope_index].borrow_mut().values.insert(k, v);
 	}
 
-	/// sets the (key, value) pair, starting from the innermost scope
+	pub fn is_constant(&self, k: &String, scope_index: usize) -> bool {
+		self.stack[scope_index].borrow().constants.contains(k)
+	}
+
+	/// Declares a constant in the current scope
+	pub fn insert_constant(&mut self, k: String, v: Object) {
+		let mut scope = self.current().borrow_mut();
+		scope.constants.insert(k.clone());
+		scope.values.insert(k, v);
+	}
+
+	/// Declares the variable in the current scope, shadowing any outer variable of the same name.
+	/// Existing variables are assigned to with `insert_at` instead
 	pub fn insert(&mut self, k: String, v: Object) {
-		// find the inner scoped variable name, then assign that
-		for i in self.stack.len()..0 {
-			if self.stack[i].contains_key(&k) {
-				self.stack[i].insert(k, v);
-				return;
-			}
-		}
-		// if it doesn't exist, assign it in the current scope
-		let len = self.stack.len();
-		self.stack[len - 1].insert(k, v);
+		let mut scope = self.current().borrow_mut();
+		scope.constants.remove(&k);
+		scope.values.insert(k, v);
+	}
+
+	/// Whether `k` is declared in the current scope, not counting outer scopes
+	pub fn is_declared(&self, k: &String) -> bool {
+		self.current().borrow().values.contains_key(k)
+	}
+
+	/// The innermost scope, which declarations go in
+	pub fn current(&self) -> &Scope {
+		&self.stack[self.stack.len() - 1]
+	}
+
+	/// The number of scopes currently entered, including the global scope
+	pub fn depth(&self) -> usize {
+		self.stack.len()
+	}
+
+	/// Exits scopes until only `depth` remain, for unwinding after an error
+	pub fn exit_to(&mut self, depth: usize) {
+		self.stack.truncate(depth);
 	}
 
 	pub fn enter_scope(&mut self) {
-		self.stack.push(HashMap::new());
+		self.stack.push(Scope::default());
 	}
 
 	pub fn exit_scope(&mut self) {
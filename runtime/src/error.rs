@@ -0,0 +1,91 @@
+//! Errors that stop the interpreter from running the rest of the code.
+//! Scripts can catch thrown values with `try`, including runtime errors which are thrown as
+//! instances of the `Error` struct.
+
+use crate::object::{Object, StructDef};
+use std::{
+	cell::RefCell,
+	fmt::{self, Display, Formatter},
+	rc::Rc,
+};
+
+#[derive(Debug, Clone)]
+pub enum Error {
+	/// A value thrown by `throw` or by a runtime error, and where it was thrown from
+	Throw {
+		value: Object,
+		ln: usize,
+		col: usize,
+	},
+	/// An error from a native function, which is thrown from the call site once it's known
+	Native(String),
+	/// Unwinds to the enclosing function, which returns the value. Made by `?` on an err result
+	Return(Object),
+	/// Stops the whole program with an exit code. Made by `exit(code)` and can't be caught
+	Exit(i32),
+}
+
+impl From<String> for Error {
+	fn from(message: String) -> Error {
+		Error::Native(message)
+	}
+}
+
+impl From<&str> for Error {
+	fn from(message: &str) -> Error {
+		Error::Native(message.to_string())
+	}
+}
+
+impl From<Error> for String {
+	fn from(e: Error) -> String {
+		e.to_string()
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Error::Throw { value, ln, col } => match message(value) {
+				Some(message) => write!(f, "(Ln {ln}, Col {col}) {message}"),
+				None => write!(f, "(Ln {ln}, Col {col}) Uncaught {}", value.repr()),
+			},
+			Error::Native(message) => write!(f, "{message}"),
+			Error::Return(value) => {
+				write!(f, "`?` returned {} outside of a function", value.repr())
+			}
+			Error::Exit(code) => write!(f, "Exited with code {code}"),
+		}
+	}
+}
+
+thread_local! {
+	static ERROR_STRUCT: Rc<StructDef> = Rc::new(StructDef {
+		name: "Error".to_string(),
+		fields: vec![
+			"message".to_string(),
+			"line".to_string(),
+			"column".to_string(),
+		],
+		methods: RefCell::new(Default::default()),
+	});
+}
+
+/// The struct that runtime errors are instances of. It's the same one every time, so errors
+/// can be told apart from instances of a script's own struct called `Error`
+pub fn error_struct() -> Rc<StructDef> {
+	ERROR_STRUCT.with(Rc::clone)
+}
+
+/// Gets the message of an `Error` instance
+fn message(value: &Object) -> Option<String> {
+	match value {
+		Object::Instance(v) if Rc::ptr_eq(&v.borrow().def, &error_struct()) => {
+			match v.borrow().get("message") {
+				Some(Object::Str(message)) => Some(message.clone()),
+				_ => None,
+			}
+		}
+		_ => None,
+	}
+}
@@ -1,213 +1,972 @@
 //! Interpreter that uses Abstract Syntax Tree walking to run code
 
-use std::{fmt::Display, vec::IntoIter};
+use std::{
+	cell::RefCell,
+	collections::HashMap,
+	fmt::Display,
+	fs,
+	path::{Path, PathBuf},
+	rc::Rc,
+	vec::IntoIter,
+};
 
 use crate::{
-	ast::*, context::Context, object::Object::*, object::*, stdlib, token::Token,
+	ast::*,
+	checker,
+	context::Context,
+	error::{self, Error},
+	iterator::Iter,
+	object::Object::*,
+	object::*,
+	resolver,
+	stdlib::{self, random::Rng},
+	token::Token,
 	token::TokenKind as tk,
 };
 
+pub use crate::stdlib::{fs::FsPolicy, input::Input, time::Clock};
+
 #[derive(Debug, Clone)]
 pub struct Interpreter {
 	curtok: Token,
 	ast: IntoIter<AstNode>,
 	pub env: Context,
 	pub stdout: String,
+	/// The struct of runtime errors
+	error_def: Rc<StructDef>,
+	/// Imported modules by their canonical path, so each one only runs once
+	modules: HashMap<PathBuf, Object>,
+	/// The files being run, with the innermost import last
+	files: Vec<PathBuf>,
+	/// Used by the random builtins
+	pub(crate) rng: Rng,
+	/// Where the input builtins read from, which is stdin unless it's set
+	pub input: Input,
+	/// Which files scripts can use, which is none unless it's set
+	pub fs: FsPolicy,
+	/// What `args()` returns, like the arguments after the script path on the command line
+	pub args: Vec<String>,
+	/// Whether `env(name)` can read environment variables
+	pub allow_env: bool,
+	/// Where the time builtins get the time from, which is the system clock unless it's set
+	pub clock: Clock,
+	/// The global scope while `eval` runs, where names can be declared again to redefine them
+	repl_scope: Option<Scope>,
 }
 
 pub fn run_source(source: &str) -> Result<(), String> {
-	use crate::{lexer::Lexer, parser::Parser};
-	Interpreter::new(Parser::new(Lexer::new(source).parse()?).parse()?).run()?;
+	Interpreter::new(parse(source)?).run()?;
 	Ok(())
 }
 
+/// Like `run_source`, but imports are resolved relative to the file
+pub fn run_file(path: impl AsRef<Path>) -> Result<(), String> {
+	Interpreter::from_file(path)?.run()?;
+	Ok(())
+}
+
+fn parse(source: &str) -> Result<Program, String> {
+	use crate::{lexer::Lexer, parser::Parser};
+	Parser::new(Lexer::new(source).parse()?).parse()
+}
+
+/// Finds mistakes that can be seen without running the program, like assigning to a constant
+/// or using a value of the wrong type. `run` and `eval` check what they run first
+pub fn check(program: &[AstNode]) -> Result<(), String> {
+	resolver::check(program)?;
+	checker::check(program)
+}
+
+impl Default for Interpreter {
+	fn default() -> Self {
+		Interpreter::new(vec![])
+	}
+}
+
 impl Interpreter {
 	pub fn new(ast: Program) -> Self {
+		let curtok = match ast.first() {
+			Some((_, token)) => token.clone(),
+			None => Token {
+				kind: tk::EOF,
+				text: String::new(),
+				ln: 1,
+				col: 1,
+			},
+		};
+
 		Self {
-			curtok: ast[0].1.clone(),
+			curtok: curtok,
 			ast: ast.into_iter(),
 			env: Context::from(stdlib::get_lib()),
 			stdout: String::new(),
+			error_def: error::error_struct(),
+			modules: HashMap::new(),
+			files: vec![],
+			rng: Rng::from_time(),
+			input: Input::Stdin,
+			fs: FsPolicy::default(),
+			args: vec![],
+			allow_env: false,
+			clock: Clock::system(),
+			repl_scope: None,
 		}
 	}
 
-	pub fn run(&mut self) -> Result<(), String> {
+	/// Reads and parses the file at `path`, which imports are resolved relative to
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+		let mut interpreter = Interpreter::new(parse(&source)?);
+		interpreter
+			.files
+			.push(path.canonicalize().map_err(|e| e.to_string())?);
+		Ok(interpreter)
+	}
+
+	/// Restarts the random builtins from `seed`, so they give the same numbers every run
+	pub fn seed(&mut self, seed: u64) {
+		self.rng = Rng::new(seed);
+	}
+
+	pub fn run(&mut self) -> Result<(), Error> {
+		check(self.ast.as_slice())?;
+
 		while let Some((statement, matching_token)) = self.ast.next() {
 			self.curtok = matching_token;
-			self.run_statement(&statement)?;
+			self.run_statement(&statement)
+				.map_err(|e| self.top_level(e))?;
 		}
 		Ok(())
 	}
 
-	fn run_statement(&mut self, statement: &Statement) -> Result<Option<Object>, String> {
+	/// Makes a Rust function callable from scripts under `name`.
+	/// Returning `Object::from_iter` from it exposes a Rust iterator to scripts.
+	pub fn define_native(&mut self, name: &str, arity: Arity, callback: NativeFunctionCallback) {
+		self.env
+			.builtins()
+			.borrow_mut()
+			.values
+			.insert(name.to_string(), stdlib::native(name, arity, callback));
+	}
+
+	/// Runs more source code on top of the existing state, like a REPL would.
+	/// Returns the repr of the last expression's value unless it was nil.
+	pub fn eval(&mut self, source: &str) -> Result<Option<String>, Error> {
+		let program = parse(source)?;
+		check(&program)?;
+
+		let depth = self.env.depth();
+		let mut last = None;
+
+		for (statement, matching_token) in program {
+			self.curtok = matching_token;
+
+			self.repl_scope = Some(self.env.globals().clone());
+			let res = match &statement {
+				Statement::Expression(expr) => self.run_expression(expr).map(Some),
+				_ => self.run_statement(&statement).map(|_| None),
+			};
+			self.repl_scope = None;
+
+			match res.map_err(|e| self.top_level(e)) {
+				Ok(value) => last = value,
+				Err(e) => {
+					// an error can leave scopes entered, so drop back to the globals
+					self.env.exit_to(depth);
+					return Err(e);
+				}
+			}
+		}
+
+		Ok(last.filter(|v| !matches!(v, Nil)).map(|v| v.repr()))
+	}
+
+	fn run_statement(&mut self, statement: &Statement) -> Result<Option<Object>, Error> {
 		match statement {
 			Statement::Expression(expr) => {
 				self.run_expression(expr)?;
 			}
-			Statement::VarDeclaration { name, value } => {
-				self.run_var(&name, value)?;
+			Statement::VarDeclaration { name, value, .. } => {
+				self.run_var(&name, value, false)?;
 			}
-			Statement::FunctionDeclaration { name, params, body } => {
-				self.env.insert(
-					name.clone(),
-					Object::Function(Box::from(FunctionDef {
-						name: name.clone(),
-						params: params.clone(),
-						body: body.clone(),
-					})),
-				);
+			Statement::ConstDeclaration { name, value, .. } => {
+				self.run_var(&name, value, true)?;
 			}
-			Statement::While { condition, body } => {
-				self.env.enter_scope();
+			Statement::FunctionDeclaration {
+				name, params, body, ..
+			} => {
+				let function = Object::Function(Rc::new(FunctionDef {
+					name: name.clone(),
+					params: params.clone(),
+					body: body.clone(),
+					globals: Rc::downgrade(self.env.globals()),
+				}));
+				self.declare(name, function, false)?;
+			}
+			Statement::StructDeclaration { name, fields } => {
+				let def = Object::Struct(Rc::new(StructDef {
+					name: name.clone(),
+					fields: fields.clone(),
+					methods: RefCell::new(HashMap::new()),
+				}));
+				self.declare(name, def, false)?;
+			}
+			Statement::Impl { name, methods } => {
+				let def = match self.env.get(name) {
+					Some(Object::Struct(def)) => def,
+					_ => return Err(self.error(format!("`{name}` is not a struct"))),
+				};
 
+				for method in methods {
+					if let Statement::FunctionDeclaration {
+						name, params, body, ..
+					} = method
+					{
+						def.methods.borrow_mut().insert(
+							name.clone(),
+							Rc::new(FunctionDef {
+								name: name.clone(),
+								params: params.clone(),
+								body: body.clone(),
+								globals: Rc::downgrade(self.env.globals()),
+							}),
+						);
+					}
+				}
+			}
+			Statement::While { condition, body } => {
 				loop {
-					if let Object::Bool(cond) = self.run_expression(condition)? {
-						if cond == false {
-							break;
-						}
+					match self.run_expression(condition)? {
+						Object::Bool(true) => {}
+						Object::Bool(false) => break,
+						_ => return Err(self.error("Expression after while isn't a boolean")),
+					}
 
-						for v in body {
-							if let Some(retval) = self.run_statement(v)? {
-								self.env.exit_scope();
-								return Ok(Some(retval));
-							}
+					// each iteration gets a fresh scope, so variables declared in the body
+					// don't carry over
+					if let Some(retval) = self.run_block(body)? {
+						return Ok(Some(retval));
+					}
+				}
+				return Ok(None);
+			}
+			Statement::For {
+				variable,
+				iterable,
+				body,
+			} => {
+				let iterable = self.run_expression(iterable)?;
+				let mut items = Iter::new(&iterable).map_err(|e| self.error(e))?;
+
+				while let Some(item) = items.next(self).map_err(|e| self.locate(e))? {
+					// each iteration gets a fresh scope so the loop variable isn't shared
+					self.env.enter_scope();
+					self.env.insert(variable.clone(), item);
+
+					for (v, _) in body {
+						if let Some(retval) = self.run_statement(v)? {
+							self.env.exit_scope();
+							return Ok(Some(retval));
 						}
-					} else {
-						return Err(self.error("Expression after while isn't a boolean"));
 					}
+
+					self.env.exit_scope();
 				}
-				self.env.exit_scope();
 				return Ok(None);
 			}
 			Statement::Return(expr) => {
 				return Ok(Some(self.run_expression(expr)?));
 			}
 			Statement::Block(block) => {
-				self.env.enter_scope();
-				for statement in block {
-					self.run_statement(statement)?;
+				return self.run_block(block);
+			}
+			Statement::Import { path, name } => {
+				let module = self.import(path)?;
+				self.declare(name, module, false)?;
+			}
+			Statement::FromImport { path, names } => {
+				let module = self.import(path)?;
+				for name in names {
+					let value = self.get_field(&module, name)?;
+					self.declare(name, value, false)?;
 				}
-				self.env.exit_scope();
+			}
+			Statement::Throw(expr) => {
+				let value = self.run_expression(expr)?;
+				return Err(Error::Throw {
+					value: value,
+					ln: self.curtok.ln,
+					col: self.curtok.col,
+				});
+			}
+			Statement::Try {
+				body,
+				catch,
+				finally,
+			} => {
+				let depth = self.env.depth();
+				let mut res = self.run_block(body).map_err(|e| self.locate(e));
+
+				if let (Err(Error::Throw { value, .. }), Some((name, handler))) = (&res, catch) {
+					// unwinding can leave scopes entered, so go back to the scope of the try
+					self.env.exit_to(depth);
+					self.env.enter_scope();
+					self.env.insert(name.clone(), value.clone());
+
+					res = self.run_block(handler);
+					if res.is_ok() {
+						self.env.exit_scope();
+					}
+				}
+
+				if let Some(finally) = finally {
+					self.env.exit_to(depth);
+					// returning or throwing from finally replaces the result of the try
+					if let Some(retval) = self.run_block(finally)? {
+						return Ok(Some(retval));
+					}
+				}
+
+				return res;
 			}
 		}
 		Ok(None)
 	}
 
-	fn run_expression(&mut self, expression: &Expression) -> Result<Object, String> {
+	/// Runs statements in a new scope, stopping early if one of them returns
+	fn run_block(&mut self, block: &Vec<AstNode>) -> Result<Option<Object>, Error> {
+		let outer_tok = self.curtok.clone();
+		self.env.enter_scope();
+
+		let mut res = Ok(None);
+		for (statement, matching_token) in block {
+			self.curtok = matching_token.clone();
+			match self.run_statement(statement) {
+				Ok(None) => {}
+				Ok(Some(retval)) => {
+					self.env.exit_scope();
+					res = Ok(Some(retval));
+					break;
+				}
+				Err(e) => {
+					res = Err(self.locate(e));
+					break;
+				}
+			}
+		}
+
+		if let Ok(None) = res {
+			self.env.exit_scope();
+		}
+		self.curtok = outer_tok;
+		res
+	}
+
+	fn run_expression(&mut self, expression: &Expression) -> Result<Object, Error> {
 		let res = match expression {
 			//Literals
-			Expression::Number(v) => Number(*v),
+			Expression::Int(v) => Int(*v),
+			Expression::Float(v) => Float(*v),
 			Expression::Str(v) => Str(v.clone()),
 			Expression::Bool(v) => Bool(*v),
+			Expression::Nil => Nil,
+			Expression::Unary(op, expr) => {
+				let value = self.run_expression(expr)?;
+
+				match (&op.kind, &value) {
+					(tk::Minus, Int(v)) => match v.checked_neg() {
+						Some(v) => Int(v),
+						None => return Err(self.error(format!("Integer overflow negating {v}"))),
+					},
+					(tk::Minus, Float(v)) => Float(-v),
+					_ => {
+						return Err(self.error(format!(
+							"Unsupported operation {:?} on {}",
+							op.kind,
+							value.type_name()
+						)))
+					}
+				}
+			}
 			//Binary Operation
 			Expression::BinOp(left, op, right) => {
 				let left = self.run_expression(left)?;
 				let right = self.run_expression(right)?;
 
-				use tk::*;
-				match (&left, &op, &right) {
-					(Number(l), Plus, Number(r)) => Number(l + r),
-					(Number(l), Minus, Number(r)) => Number(l - r),
-					(Number(l), Star, Number(r)) => Number(l * r),
-					(Number(l), Slash, Number(r)) => Number(l / r),
-					(Number(l), Equals, Number(r)) => Bool(l == r),
-					(Number(l), LessThan, Number(r)) => Bool(l < r),
-					(Number(l), GreaterThan, Number(r)) => Bool(l > r),
-					(Number(l), NotEquals, Number(r)) => Bool(l != r),
-					(Bool(l), Equals, Bool(r)) => Bool(l == r),
-					(Bool(l), NotEquals, Bool(r)) => Bool(l != r),
-					_ => {
-						return Err(self.error(format!(
-							"Unsupported operation {:?} between {} and {}",
-							op, left, right
-						)));
+				self.binary_op(&left, &op.kind, &right)?
+			}
+			Expression::Call {
+				name, args, named, ..
+			} => {
+				let (args, named) = self.run_arguments(args, named)?;
+				self.run_function(&name, &args, named)?
+			}
+			Expression::CallValue {
+				callee,
+				args,
+				named,
+			} => {
+				let callee = self.run_expression(callee)?;
+				let (args, named) = self.run_arguments(args, named)?;
+				self.call_named(&callee, &args, named)?
+			}
+			Expression::StructLiteral { name, fields } => {
+				let def = match self.env.get(name) {
+					Some(Object::Struct(def)) => def,
+					_ => return Err(self.error(format!("`{name}` is not a struct"))),
+				};
+
+				let mut values = vec![None; def.fields.len()];
+
+				for (field, value) in fields {
+					let index = match def.fields.iter().position(|v| v == field) {
+						Some(i) => i,
+						None => return Err(self.error(format!("`{name}` has no field `{field}`"))),
+					};
+					if values[index].is_some() {
+						return Err(self.error(format!("Field `{field}` is given twice")));
+					}
+					values[index] = Some(self.run_expression(value)?);
+				}
+
+				let mut fields = vec![];
+				for (field, value) in def.fields.iter().zip(values) {
+					match value {
+						Some(v) => fields.push(v),
+						None => return Err(self.error(format!("Field `{field}` is missing"))),
+					}
+				}
+
+				new_instance(def, fields)
+			}
+			Expression::MethodCall {
+				object,
+				method,
+				args,
+				named,
+				optional,
+			} => {
+				let object = self.run_expression(object)?;
+				if *optional && object == Nil {
+					return Ok(Nil);
+				}
+
+				let (args, named) = self.run_arguments(args, named)?;
+				self.call_method(&object, method, args, named)?
+			}
+			Expression::Get {
+				object,
+				field,
+				optional,
+			} => {
+				let object = self.run_expression(object)?;
+				if *optional && object == Nil {
+					return Ok(Nil);
+				}
+
+				self.get_field(&object, field)?
+			}
+			Expression::Try(expr) => match self.run_expression(expr)? {
+				Outcome { ok: true, value } => *value,
+				v @ Outcome { ok: false, .. } => return Err(Error::Return(v)),
+				v => {
+					return Err(self.error(format!(
+						"`?` expects a result, instead got {}",
+						v.type_name()
+					)))
+				}
+			},
+			Expression::Coalesce(left, right) => match self.run_expression(left)? {
+				Nil => self.run_expression(right)?,
+				v => v,
+			},
+			Expression::List(items) => {
+				let mut list = vec![];
+
+				for item in items {
+					list.push(self.run_expression(item)?);
+				}
+
+				Object::list(list)
+			}
+			Expression::Index(target, index) => {
+				let target = self.run_expression(target)?;
+				let index = self.run_expression(index)?;
+
+				match &target {
+					Str(v) => {
+						let i = self.to_index(&index, v.chars().count(), false)?;
+						Str(v.chars().nth(i).unwrap().to_string())
 					}
+					List(v) => {
+						let i = self.to_index(&index, v.borrow().len(), false)?;
+						v.borrow()[i].clone()
+					}
+					Map(v) => {
+						let key = self.to_key(&index)?;
+						match v.borrow().get(&key) {
+							Some(value) => value.clone(),
+							None => return Err(self.error(format!("The map has no key {key:?}"))),
+						}
+					}
+					_ => return Err(self.error(format!("Can't index into {}", target.type_name()))),
 				}
 			}
-			Expression::Call { name, args } => {
-				let mut processed_args = vec![];
+			Expression::Slice { target, start, end } => {
+				let target = self.run_expression(target)?;
+				let len = match &target {
+					Str(v) => v.chars().count(),
+					List(v) => v.borrow().len(),
+					_ => return Err(self.error(format!("Can't slice {}", target.type_name()))),
+				};
+
+				let start = match start {
+					Some(v) => {
+						let v = self.run_expression(v)?;
+						self.to_index(&v, len, true)?
+					}
+					None => 0,
+				};
+				let end = match end {
+					Some(v) => {
+						let v = self.run_expression(v)?;
+						self.to_index(&v, len, true)?
+					}
+					None => len,
+				};
 
-				for arg in args.into_iter() {
-					processed_args.push(self.run_expression(arg)?);
+				if start > end {
+					return Err(self.error(format!("Slice start {start} is after its end {end}")));
 				}
 
-				self.run_function(&name, &processed_args)?
+				match &target {
+					Str(v) => Str(v.chars().skip(start).take(end - start).collect()),
+					List(v) => Object::list(v.borrow()[start..end].to_vec()),
+					_ => unreachable!(),
+				}
 			}
 			Expression::Identifier(name) => {
 				if let Some(val) = self.env.get(&name) {
-					val.clone()
+					val
 				} else {
-					dbg!(&self.env);
 					return Err(self.error(format!("Identifier `{name}` does not exist")));
 				}
 			}
-			Expression::Assign(name, value) => self.run_assign(name, value)?,
+			Expression::Assign(name, _, value) => self.run_assign(name, None, value)?,
+			Expression::CompoundAssign(name, op, value) => {
+				self.run_assign(name, Some(&op.kind), value)?
+			}
 		};
 		return Ok(res);
 	}
 
-	fn run_function(&mut self, name: &String, args: &Vec<Object>) -> Result<Object, String> {
-		if let Some(_) = self.env.contains(name) {
-			let v = self.env.get(name).unwrap().clone();
-			match v {
-				Object::NativeFunction(f) => {
-					if args.len() < 1 {
-						return Err(
-							self.error(format!("No arguments provided to function `{name}`"))
-						);
-					}
-					let retval = (f.callback)(args, self);
-					return Ok(retval);
+	fn binary_op(&self, left: &Object, op: &tk, right: &Object) -> Result<Object, Error> {
+		let res = match (left, op, right) {
+			(_, tk::Equals, _) => Bool(left == right),
+			(_, tk::NotEquals, _) => Bool(left != right),
+			(_, tk::LessThan | tk::GreaterThan | tk::LessEquals | tk::GreaterEquals, _) => {
+				return self.compare(left, op, right)
+			}
+			(Int(l), _, Int(r)) => return self.int_op(*l, op, *r),
+			// mixing ints with floats promotes the int
+			(Int(_) | Float(_), _, Int(_) | Float(_)) => {
+				return self.float_op(as_float(left), op, as_float(right))
+			}
+			(Str(l), tk::Plus, Str(r)) => Str(format!("{l}{r}")),
+			(Bool(l), tk::Ampersand, Bool(r)) => Bool(l & r),
+			(Bool(l), tk::Pipe, Bool(r)) => Bool(l | r),
+			(Bool(l), tk::Caret, Bool(r)) => Bool(l ^ r),
+			_ => return Err(self.unsupported(left, op, right)),
+		};
+		Ok(res)
+	}
+
+	/// Handles `<`, `>`, `<=` and `>=` for the types that have an order.
+	/// NaN has no order, so comparing with it is false rather than an error.
+	fn compare(&self, left: &Object, op: &tk, right: &Object) -> Result<Object, Error> {
+		let ord = match left.partial_cmp(right) {
+			Some(ord) => ord,
+			None if is_number(left) && is_number(right) => return Ok(Bool(false)),
+			None => {
+				return Err(self.error(format!(
+					"Can't compare {} with {}",
+					left.type_name(),
+					right.type_name()
+				)))
+			}
+		};
+
+		let res = match op {
+			tk::LessThan => ord.is_lt(),
+			tk::GreaterThan => ord.is_gt(),
+			tk::LessEquals => ord.is_le(),
+			_ => ord.is_ge(),
+		};
+		Ok(Bool(res))
+	}
+
+	/// Integer arithmetic is exact, so overflow is an error instead of wrapping.
+	/// Division truncates towards zero and `%` takes the sign of the left side.
+	/// A negative exponent gives a float, like mixing in a float would.
+	fn int_op(&self, l: i64, op: &tk, r: i64) -> Result<Object, Error> {
+		let res = match op {
+			tk::Plus => l.checked_add(r),
+			tk::Minus => l.checked_sub(r),
+			tk::Star => l.checked_mul(r),
+			tk::Slash | tk::Percent if r == 0 => {
+				return Err(self.error(format!("Integer division by zero in {l} {op:?} {r}")))
+			}
+			tk::Slash => l.checked_div(r),
+			tk::Percent => l.checked_rem(r),
+			tk::StarStar if r < 0 => return self.float_op(l as f64, op, r as f64),
+			tk::StarStar => u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+			tk::Ampersand => Some(l & r),
+			tk::Pipe => Some(l | r),
+			tk::Caret => Some(l ^ r),
+			tk::ShiftLeft | tk::ShiftRight if !(0..64).contains(&r) => {
+				return Err(self.error(format!("Shift amount {r} isn't between 0 and 63")))
+			}
+			tk::ShiftLeft => Some(l << r),
+			tk::ShiftRight => Some(l >> r),
+			_ => return Err(self.unsupported(&Int(l), op, &Int(r))),
+		};
+
+		match res {
+			Some(v) => Ok(Int(v)),
+			None => Err(self.error(format!("Integer overflow in {l} {op:?} {r}"))),
+		}
+	}
+
+	fn float_op(&self, l: f64, op: &tk, r: f64) -> Result<Object, Error> {
+		let res = match op {
+			tk::Plus => Float(l + r),
+			tk::Minus => Float(l - r),
+			tk::Star => Float(l * r),
+			tk::Slash => Float(l / r),
+			tk::Percent => Float(l % r),
+			tk::StarStar => Float(l.powf(r)),
+			_ => return Err(self.unsupported(&Float(l), op, &Float(r))),
+		};
+		Ok(res)
+	}
+
+	fn unsupported(&self, left: &Object, op: &tk, right: &Object) -> Error {
+		self.error(format!(
+			"Unsupported operation {:?} between {} and {}",
+			op,
+			left.type_name(),
+			right.type_name()
+		))
+	}
+
+	fn run_arguments(
+		&mut self,
+		args: &Vec<Expression>,
+		named: &Vec<(String, Expression)>,
+	) -> Result<(Vec<Object>, Vec<(String, Object)>), Error> {
+		let mut processed_args = vec![];
+		for arg in args {
+			processed_args.push(self.run_expression(arg)?);
+		}
+
+		let mut processed_named = vec![];
+		for (name, arg) in named {
+			processed_named.push((name.clone(), self.run_expression(arg)?));
+		}
+
+		Ok((processed_args, processed_named))
+	}
+
+	fn run_function(
+		&mut self,
+		name: &String,
+		args: &Vec<Object>,
+		named: Vec<(String, Object)>,
+	) -> Result<Object, Error> {
+		match self.env.get(name) {
+			Some(v @ (Object::Function(_) | Object::NativeFunction(_) | Object::Struct(_))) => {
+				self.call_named(&v, args, named)
+			}
+			Some(_) => Err(self.error(format!("`{name}` is not a function"))),
+			None => Err(self.error(format!("`{name}` is not defined"))),
+		}
+	}
+
+	/// Calls a function value, like one passed to a native function as an argument
+	pub(crate) fn call(&mut self, function: &Object, args: &Vec<Object>) -> Result<Object, Error> {
+		self.call_named(function, args, vec![])
+	}
+
+	/// Calls a function with named arguments as well, which only script functions take
+	fn call_named(
+		&mut self,
+		function: &Object,
+		args: &Vec<Object>,
+		named: Vec<(String, Object)>,
+	) -> Result<Object, Error> {
+		let name = match function {
+			Object::NativeFunction(f) => &f.name,
+			Object::Struct(def) => &def.name,
+			_ => "",
+		};
+		if !named.is_empty() && !name.is_empty() {
+			return Err(self.error(format!("`{name}` doesn't take named arguments")));
+		}
+
+		match function {
+			Object::NativeFunction(f) => {
+				if !f.arity.accepts(args.len()) {
+					return Err(self.arity_error(&f.name, f.arity, args.len()));
 				}
-				Object::Function(f) => {
-					if f.params.len() != args.len() {
-						return Err(self.error(format!(
-							"Arguments of length {} don't match parameters of length {}",
-							args.len(),
-							f.params.len()
-						)));
+				return (f.callback)(args, self).map_err(|e| self.locate(e));
+			}
+			Object::Function(f) => {
+				// a function from another module can't see the caller's variables, only its
+				// module's globals
+				let caller_env = match f.globals.upgrade() {
+					Some(globals) if !Rc::ptr_eq(&globals, self.env.globals()) => {
+						let env = self.env.with_globals(globals);
+						Some(std::mem::replace(&mut self.env, env))
 					}
+					_ => None,
+				};
 
-					self.env.enter_scope();
+				let res = self.run_body(f, args.clone(), named);
 
-					for i in 0..f.params.len() {
-						self.env.insert(f.params[i].clone(), args[i].clone());
-					}
+				if let Some(env) = caller_env {
+					self.env = env;
+				}
+				return res;
+			}
+			// calling a struct makes an instance from the fields in order
+			Object::Struct(def) => {
+				if def.fields.len() != args.len() {
+					return Err(self.error(format!(
+						"`{}` has {} fields, but {}",
+						def.name,
+						def.fields.len(),
+						given(args.len())
+					)));
+				}
+				Ok(new_instance(def.clone(), args.clone()))
+			}
+			_ => Err(self.error(format!("{} is not a function", function.type_name()))),
+		}
+	}
 
-					let mut retval = Object::Unit;
+	fn run_body(
+		&mut self,
+		f: &FunctionDef,
+		args: Vec<Object>,
+		named: Vec<(String, Object)>,
+	) -> Result<Object, Error> {
+		let depth = self.env.depth();
+		self.env.enter_scope();
 
-					for v in f.body {
-						if let Some(_retval) = self.run_statement(&v)? {
-							retval = _retval;
-							break;
-						}
-					}
+		if let Err(e) = self.bind_params(f, args, named) {
+			self.env.exit_scope();
+			return Err(e);
+		}
 
-					self.env.exit_scope();
+		let mut retval = Object::Nil;
+		let outer_tok = self.curtok.clone();
 
-					return Ok(retval);
+		for (v, matching_token) in &f.body {
+			self.curtok = matching_token.clone();
+			match self.run_statement(&v) {
+				Ok(Some(_retval)) => {
+					retval = _retval;
+					break;
+				}
+				Ok(None) => {}
+				// `?` can return from inside nested blocks, so their scopes are dropped too
+				Err(Error::Return(_retval)) => {
+					self.env.exit_to(depth + 1);
+					retval = _retval;
+					break;
+				}
+				Err(e) => {
+					let e = self.locate(e);
+					self.curtok = outer_tok;
+					return Err(e);
+				}
+			}
+		}
+
+		self.env.exit_scope();
+		self.curtok = outer_tok;
+
+		return Ok(retval);
+	}
+
+	/// Declares the parameters of `f` from the arguments it was called with. Positional
+	/// arguments come first, then named ones, then defaults for the parameters that are left
+	fn bind_params(
+		&mut self,
+		f: &FunctionDef,
+		mut args: Vec<Object>,
+		mut named: Vec<(String, Object)>,
+	) -> Result<(), Error> {
+		let positional = f.params.iter().filter(|v| !v.rest).count();
+		let mut rest = match args.len() > positional {
+			true => args.split_off(positional),
+			false => vec![],
+		};
+
+		if !rest.is_empty() && !f.params.iter().any(|v| v.rest) {
+			return Err(self.too_many_args(&f.name, positional, positional + rest.len()));
+		}
+
+		for (name, _) in &named {
+			match f.params.iter().position(|v| v.name == *name && !v.rest) {
+				Some(i) if i < args.len() => {
+					return Err(self.error(format!(
+						"Parameter `{name}` of `{}` is given more than once",
+						f.name
+					)))
+				}
+				Some(_) => {}
+				None => {
+					return Err(self.error(format!("`{}` has no parameter named `{name}`", f.name)))
 				}
-				_ => Err(self.error(format!("`{name}` is not a function"))),
 			}
-		} else {
-			Err(self.error(format!("`{name}` is not defined")))
+		}
+
+		let mut args = args.into_iter();
+		for param in &f.params {
+			let value = if param.rest {
+				Object::list(std::mem::take(&mut rest))
+			} else if let Some(v) = args.next() {
+				v
+			} else if let Some(i) = named.iter().position(|(v, _)| *v == param.name) {
+				named.remove(i).1
+			} else if let Some(default) = &param.default {
+				self.run_expression(default)?
+			} else {
+				return Err(self.error(format!(
+					"`{}` is missing an argument for parameter `{}`",
+					f.name, param.name
+				)));
+			};
+			self.env.insert(param.name.clone(), value);
+		}
+
+		// named arguments are removed as they're used, so any left were repeated
+		match named.first() {
+			Some((name, _)) => Err(self.error(format!(
+				"Parameter `{name}` of `{}` is given more than once",
+				f.name
+			))),
+			None => Ok(()),
 		}
 	}
 
-	fn run_var(&mut self, name: &String, value: &Expression) -> Result<(), String> {
+	/// Runs the file at `path` as a module the first time it's imported, then returns the
+	/// same module every time after
+	fn import(&mut self, path: &String) -> Result<Object, Error> {
+		// relative paths start from the directory of the importing file
+		let full_path = match self.files.last().and_then(|v| v.parent()) {
+			Some(dir) => dir.join(path),
+			None => PathBuf::from(path),
+		};
+		let full_path = full_path
+			.canonicalize()
+			.map_err(|e| self.error(format!("Can't import \"{path}\": {e}")))?;
+
+		if let Some(module) = self.modules.get(&full_path) {
+			return Ok(module.clone());
+		}
+
+		if let Some(start) = self.files.iter().position(|v| *v == full_path) {
+			let cycle: Vec<String> = self.files[start..]
+				.iter()
+				.chain([&full_path])
+				.map(|v| v.display().to_string())
+				.collect();
+			return Err(self.error(format!("Import cycle: {}", cycle.join(" -> "))));
+		}
+
+		let source = fs::read_to_string(&full_path)
+			.map_err(|e| self.error(format!("Can't import \"{path}\": {e}")))?;
+		let program = parse(&source)
+			.and_then(|v| check(&v).map(|_| v))
+			.map_err(|e| self.error(format!("In \"{path}\": {e}")))?;
+
+		// the module runs in its own global scope, then the importer carries on where it was
+		let globals = Scope::default();
+		let env = self.env.with_globals(globals.clone());
+		let outer_env = std::mem::replace(&mut self.env, env);
+		let outer_tok = self.curtok.clone();
+		self.files.push(full_path.clone());
+
+		let mut res = Ok(None);
+		for (statement, matching_token) in &program {
+			self.curtok = matching_token.clone();
+			res = self.run_statement(statement).map_err(|e| self.top_level(e));
+			if res.is_err() {
+				break;
+			}
+		}
+
+		self.files.pop();
+		self.curtok = outer_tok;
+		self.env = outer_env;
+		res?;
+
+		let module = Object::Module(Rc::new(crate::object::Module {
+			path: path.clone(),
+			globals: globals,
+		}));
+		self.modules.insert(full_path, module.clone());
+		Ok(module)
+	}
+
+	fn run_var(&mut self, name: &String, value: &Expression, constant: bool) -> Result<(), Error> {
 		let right = self.run_expression(value)?;
-		self.env.insert(name.clone(), right);
+		self.declare(name, right, constant)
+	}
+
+	/// Declares a name in the current scope. Names from outer scopes can be shadowed, but a
+	/// name can't be declared twice in the same scope, except at the top level of `eval`
+	fn declare(&mut self, name: &String, value: Object, constant: bool) -> Result<(), Error> {
+		let redefining = match &self.repl_scope {
+			Some(scope) => Rc::ptr_eq(scope, self.env.current()),
+			None => false,
+		};
+		if self.env.is_declared(name) && !redefining {
+			return Err(self.error(format!("`{name}` is already declared in this scope")));
+		}
+
+		match constant {
+			true => self.env.insert_constant(name.clone(), value),
+			false => self.env.insert(name.clone(), value),
+		}
 		Ok(())
 	}
 
-	fn run_assign(&mut self, name: &Expression, value: &Expression) -> Result<Object, String> {
-		let new_value = self.run_expression(value)?;
+	/// Assigns to an identifier or list element. With an `op` the current value is
+	/// combined with the new one, evaluating the target's expressions only once.
+	fn run_assign(
+		&mut self,
+		name: &Expression,
+		op: Option<&tk>,
+		value: &Expression,
+	) -> Result<Object, Error> {
+		let mut new_value = self.run_expression(value)?;
 
 		if let Expression::Identifier(name) = &name {
 			match self.env.contains(name) {
 				Some(scope_index) => {
+					if self.env.is_constant(name, scope_index) {
+						return Err(self.error(format!("Can't assign to constant `{name}`")));
+					}
+					if let Some(op) = op {
+						new_value = self.binary_op(&self.env.get(name).unwrap(), op, &new_value)?;
+					}
 					self.env.insert_at(name.clone(), new_value, scope_index);
-					return Ok(Object::Unit);
+					return Ok(Object::Nil);
 				}
 				None => {
 					return Err(self.error(format!("Identifier `{name}` hasn't been declared")));
@@ -215,10 +974,253 @@ impl Interpreter {
 			}
 		}
 
-		Err(format!("`{name:?}` is not an identifier"))
+		if let Expression::Get {
+			object,
+			field,
+			optional: false,
+		} = &name
+		{
+			let object = self.run_expression(object)?;
+
+			if let Instance(instance) = &object {
+				let index = match instance.borrow().field_index(field) {
+					Some(i) => i,
+					None => return Err(self.no_field(&object, field)),
+				};
+				if let Some(op) = op {
+					new_value = self.binary_op(&instance.borrow().fields[index], op, &new_value)?;
+				}
+				instance.borrow_mut().fields[index] = new_value;
+				return Ok(Object::Nil);
+			}
+
+			return Err(self.error(format!("Can't assign to a field of {}", object.type_name())));
+		}
+
+		if let Expression::Index(target, index) = &name {
+			let target = self.run_expression(target)?;
+			let index = self.run_expression(index)?;
+
+			if let List(list) = &target {
+				let i = self.to_index(&index, list.borrow().len(), false)?;
+				if let Some(op) = op {
+					new_value = self.binary_op(&list.borrow()[i], op, &new_value)?;
+				}
+				list.borrow_mut()[i] = new_value;
+				return Ok(Object::Nil);
+			}
+
+			// assigning to a key that isn't in the map adds it, unless it's a compound assign
+			if let Map(map) = &target {
+				let key = self.to_key(&index)?;
+				let current = map.borrow().get(&key).cloned();
+				match (current, op) {
+					(Some(current), Some(op)) => {
+						new_value = self.binary_op(&current, op, &new_value)?;
+						map.borrow_mut().insert(key, new_value);
+					}
+					(None, Some(_)) => {
+						return Err(self.error(format!("The map has no key {key:?}")))
+					}
+					(_, None) => map.borrow_mut().insert(key, new_value),
+				}
+				return Ok(Object::Nil);
+			}
+
+			return Err(self.error(format!(
+				"Can't assign to an index of {}",
+				target.type_name()
+			)));
+		}
+
+		Err(self.error(format!("`{name:?}` can't be assigned to")))
+	}
+
+	/// Instances look up methods from their struct, passing themselves as the first argument.
+	/// Other values have builtin methods that call the stdlib function of the same name.
+	pub(crate) fn call_method(
+		&mut self,
+		object: &Object,
+		name: &String,
+		mut args: Vec<Object>,
+		named: Vec<(String, Object)>,
+	) -> Result<Object, Error> {
+		match object {
+			Instance(instance) => {
+				let method = instance.borrow().method(name);
+				if let Some(method) = method {
+					// the instance is the first argument, but it isn't counted in errors
+					let positional = method.params.iter().filter(|v| !v.rest).count();
+					let rest = method.params.iter().any(|v| v.rest);
+					if !rest && positional > 0 && args.len() >= positional {
+						return Err(self.too_many_args(name, positional - 1, args.len()));
+					}
+					args.insert(0, object.clone());
+					return self.call_named(&Object::Function(method), &args, named);
+				}
+
+				// a function stored in a field is called without passing the instance
+				let field = instance.borrow().get(name).cloned();
+				match field {
+					Some(field) => self.call_named(&field, &args, named),
+					None => Err(self.error(format!(
+						"`{}` has no method `{name}`",
+						instance.borrow().def.name
+					))),
+				}
+			}
+			Object::Module(_) => {
+				let function = self.get_field(object, name)?;
+				self.call_named(&function, &args, named)
+			}
+			// methods called on the struct itself don't get an instance
+			Object::Struct(def) => {
+				let method = def.methods.borrow().get(name).cloned();
+				match method {
+					Some(method) => self.call_named(&Object::Function(method), &args, named),
+					None => Err(self.error(format!("`{}` has no method `{name}`", def.name))),
+				}
+			}
+			_ => match stdlib::method(object, name) {
+				Some(method) => {
+					// the receiver is the first argument, but it isn't counted in errors.
+					// Named arguments are reported by `call_named` first
+					if let NativeFunction(f) = &method {
+						if named.is_empty() && !f.arity.accepts(args.len() + 1) {
+							let arity = f.arity.without_receiver();
+							return Err(self.arity_error(&f.name, arity, args.len()));
+						}
+					}
+					args.insert(0, object.clone());
+					self.call_named(&method, &args, named)
+				}
+				None => Err(self.error(format!("{} has no method `{name}`", object.type_name()))),
+			},
+		}
+	}
+
+	fn get_field(&self, object: &Object, field: &String) -> Result<Object, Error> {
+		match object {
+			Object::Module(module) => match module.globals.borrow().values.get(field) {
+				Some(v) => Ok(v.clone()),
+				None => Err(self.error(format!("Module \"{}\" has no `{field}`", module.path))),
+			},
+			Instance(instance) => match instance.borrow().get(field) {
+				Some(v) => Ok(v.clone()),
+				None => Err(self.no_field(object, field)),
+			},
+			_ => Err(self.error(format!(
+				"Can't get field `{field}` of {}",
+				object.type_name()
+			))),
+		}
+	}
+
+	fn arity_error(&self, name: &str, arity: Arity, count: usize) -> Error {
+		self.error(format!("`{name}` takes {arity}, but {}", given(count)))
+	}
+
+	fn too_many_args(&self, name: &str, max: usize, count: usize) -> Error {
+		let arguments = match max {
+			1 => "argument",
+			_ => "arguments",
+		};
+		self.error(format!(
+			"`{name}` takes at most {max} {arguments}, but {}",
+			given(count)
+		))
+	}
+
+	fn no_field(&self, object: &Object, field: &String) -> Error {
+		match object {
+			Instance(v) => self.error(format!("`{}` has no field `{field}`", v.borrow().def.name)),
+			_ => self.error(format!("{} has no field `{field}`", object.type_name())),
+		}
+	}
+
+	/// Checks that `key` can index a map, which only has string keys
+	fn to_key(&self, key: &Object) -> Result<String, Error> {
+		match key {
+			Str(v) => Ok(v.clone()),
+			_ => Err(self.error(format!(
+				"Map keys must be strings, instead got {}",
+				key.type_name()
+			))),
+		}
+	}
+
+	/// Converts `index` into a position within a sequence of length `len`.
+	/// Slice bounds may also point one past the last element.
+	fn to_index(&self, index: &Object, len: usize, is_bound: bool) -> Result<usize, Error> {
+		let i = match index {
+			Int(v) if *v >= 0 => *v as usize,
+			_ => {
+				return Err(self.error(format!(
+					"Index must be a non-negative int, instead got {index}"
+				)))
+			}
+		};
+
+		if i < len || (is_bound && i == len) {
+			Ok(i)
+		} else {
+			Err(self.error(format!("Index {i} is out of range for length {len}")))
+		}
+	}
+
+	/// Makes a runtime error, which is thrown as an `Error` instance
+	fn error<S: Into<String> + Display>(&self, text: S) -> Error {
+		Error::Throw {
+			value: self.error_object(text.into()),
+			ln: self.curtok.ln,
+			col: self.curtok.col,
+		}
+	}
+
+	/// Makes an instance of the `Error` struct positioned at the current token
+	pub(crate) fn error_object(&self, message: String) -> Object {
+		new_instance(
+			self.error_def.clone(),
+			vec![
+				Str(message),
+				Int(self.curtok.ln as i64),
+				Int(self.curtok.col as i64),
+			],
+		)
+	}
+
+	/// `?` can't return from the top level, so it becomes an error there
+	fn top_level(&self, e: Error) -> Error {
+		match e {
+			Error::Return(_) => self.error(e.to_string()),
+			e => e,
+		}
 	}
 
-	fn error<S: Into<String> + Display>(&self, text: S) -> String {
-		format!("(Ln {}, Col {}) {}", self.curtok.ln, self.curtok.col, text)
+	/// Gives errors from native functions the position of the call
+	fn locate(&self, e: Error) -> Error {
+		match e {
+			Error::Native(message) => self.error(message),
+			e => e,
+		}
+	}
+}
+
+fn new_instance(def: Rc<StructDef>, fields: Vec<Object>) -> Object {
+	Object::Instance(Rc::new(RefCell::new(crate::object::Instance {
+		def: def,
+		fields: fields,
+	})))
+}
+
+fn is_number(v: &Object) -> bool {
+	matches!(v, Int(_) | Float(_))
+}
+
+fn as_float(number: &Object) -> f64 {
+	match number {
+		Int(v) => *v as f64,
+		Float(v) => *v,
+		_ => unreachable!("{number:?} isn't a number"),
 	}
 }
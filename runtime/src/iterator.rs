@@ -0,0 +1,161 @@
+//! The iterator protocol used by `for` loops and the functions that consume iterables.
+//!
+//! Besides the built in collections, a function can be iterated over. It's called with no
+//! arguments for every item until it returns the sentinel value `nil`.
+//! Instances with a `next` method or field are iterated over the same way.
+//! Host code can expose any Rust `Iterator` to scripts with `Object::from_iter`.
+
+use crate::{
+	error::Error,
+	interpreter::Interpreter,
+	object::{Object, Object::*},
+};
+use std::{
+	cell::RefCell,
+	fmt::{self, Debug, Formatter},
+	rc::Rc,
+};
+
+pub enum Iter {
+	/// Lists are read by position, so changes made while looping are seen
+	List(Rc<RefCell<Vec<Object>>>, usize),
+	Chars(std::vec::IntoIter<char>),
+	Range {
+		next: i64,
+		end: i64,
+		step: i64,
+	},
+	/// Calls a script function until it returns `nil`
+	Function(Object),
+	/// Calls the `next` method of an instance until it returns `nil`
+	Method(Object),
+	/// An iterator provided by the host
+	Native(Box<dyn std::iter::Iterator<Item = Object>>),
+	/// An iterator object, which is shared by everything that holds it
+	Shared(Rc<RefCell<Iter>>),
+	Map(Box<Iter>, Object),
+	Filter(Box<Iter>, Object),
+	Zip(Box<Iter>, Box<Iter>),
+	Enumerate(Box<Iter>, i64),
+	Take(Box<Iter>, usize),
+}
+
+impl Iter {
+	pub fn new(iterable: &Object) -> Result<Iter, String> {
+		match iterable {
+			List(v) => Ok(Iter::List(v.clone(), 0)),
+			// loops over the keys as they were when it started
+			Map(v) => {
+				let keys = v.borrow().iter().map(|(k, _)| Str(k.clone())).collect();
+				Ok(Iter::List(Rc::new(RefCell::new(keys)), 0))
+			}
+			Str(v) => Ok(Iter::Chars(v.chars().collect::<Vec<_>>().into_iter())),
+			Range { start, end, step } => Ok(Iter::Range {
+				next: *start,
+				end: *end,
+				step: *step,
+			}),
+			Function(_) | NativeFunction(_) => Ok(Iter::Function(iterable.clone())),
+			Instance(v)
+				if v.borrow().method("next").is_some() || v.borrow().get("next").is_some() =>
+			{
+				Ok(Iter::Method(iterable.clone()))
+			}
+			Iterator(v) => Ok(Iter::Shared(v.clone())),
+			_ => Err(format!("Can't iterate over {}", iterable.type_name())),
+		}
+	}
+
+	/// Returns the next item, or `None` once the iterator is exhausted.
+	/// Functions given to adapters like `map` are called by this, so it can fail.
+	pub fn next(&mut self, i: &mut Interpreter) -> Result<Option<Object>, Error> {
+		let item = match self {
+			Iter::List(list, index) => {
+				let item = list.borrow().get(*index).cloned();
+				*index += 1;
+				item
+			}
+			Iter::Chars(chars) => chars.next().map(|c| Str(c.to_string())),
+			Iter::Range { next, end, step } => {
+				let done = if *step > 0 { next >= end } else { next <= end };
+				if done {
+					return Ok(None);
+				}
+
+				let item = Int(*next);
+				// stepping past the end of the ints also finishes the range
+				*next = next.checked_add(*step).unwrap_or(*end);
+				Some(item)
+			}
+			Iter::Function(f) => match i.call(f, &vec![])? {
+				Nil => None,
+				v => Some(v),
+			},
+			Iter::Method(object) => {
+				match i.call_method(object, &"next".to_string(), vec![], vec![])? {
+					Nil => None,
+					v => Some(v),
+				}
+			}
+			Iter::Native(iter) => iter.next(),
+			Iter::Shared(iter) => match iter.try_borrow_mut() {
+				Ok(mut iter) => iter.next(i)?,
+				Err(_) => return Err("An iterator can't be advanced while it's in use".into()),
+			},
+			Iter::Map(iter, f) => match iter.next(i)? {
+				Some(v) => Some(i.call(f, &vec![v])?),
+				None => None,
+			},
+			Iter::Filter(iter, f) => loop {
+				match iter.next(i)? {
+					Some(v) => {
+						if i.call(f, &vec![v.clone()])? == Bool(true) {
+							break Some(v);
+						}
+					}
+					None => break None,
+				}
+			},
+			Iter::Zip(left, right) => match (left.next(i)?, right.next(i)?) {
+				(Some(l), Some(r)) => Some(Object::list(vec![l, r])),
+				_ => None,
+			},
+			Iter::Enumerate(iter, count) => match iter.next(i)? {
+				Some(v) => {
+					let item = Object::list(vec![Int(*count), v]);
+					*count += 1;
+					Some(item)
+				}
+				None => None,
+			},
+			Iter::Take(iter, remaining) => {
+				if *remaining == 0 {
+					return Ok(None);
+				}
+				*remaining -= 1;
+				iter.next(i)?
+			}
+		};
+		Ok(item)
+	}
+}
+
+impl Debug for Iter {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		let name = match self {
+			Iter::List(..) => "List",
+			Iter::Chars(..) => "Chars",
+			Iter::Range { .. } => "Range",
+			Iter::Function(..) => "Function",
+			Iter::Method(..) => "Method",
+			Iter::Native(..) => "Native",
+			Iter::Shared(..) => "Shared",
+			Iter::Map(..) => "Map",
+			Iter::Filter(..) => "Filter",
+			Iter::Zip(..) => "Zip",
+			Iter::Enumerate(..) => "Enumerate",
+			Iter::Take(..) => "Take",
+		};
+		write!(f, "Iter::{name}")
+	}
+}
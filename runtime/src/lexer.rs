@@ -34,14 +34,75 @@ impl Lexer {
 
 			match ch {
 				// operators
-				'+' => self.add_token(Plus),
-				'-' => self.add_token(Minus),
-				'*' => self.add_token(Star),
-				'/' => self.add_token(Slash),
+				'+' => {
+					if self.curch_is('=') {
+						self.advance();
+						self.add_token(PlusAssign);
+					} else {
+						self.add_token(Plus);
+					}
+				}
+				'-' => {
+					if self.curch_is('=') {
+						self.advance();
+						self.add_token(MinusAssign);
+					} else if self.curch_is('>') {
+						self.advance();
+						self.add_token(Arrow);
+					} else {
+						self.add_token(Minus);
+					}
+				}
+				'*' => {
+					if self.curch_is('*') {
+						self.advance();
+						self.add_token(StarStar);
+					} else if self.curch_is('=') {
+						self.advance();
+						self.add_token(StarAssign);
+					} else {
+						self.add_token(Star);
+					}
+				}
+				'/' => {
+					if self.curch_is('=') {
+						self.advance();
+						self.add_token(SlashAssign);
+					} else {
+						self.add_token(Slash);
+					}
+				}
+				'?' => {
+					if self.curch_is('?') {
+						self.advance();
+						self.add_token(Coalesce);
+					} else if self.curch_is('.') {
+						self.advance();
+						self.add_token(QuestionDot);
+					} else {
+						self.add_token(Question);
+					}
+				}
+				'&' => self.add_token(Ampersand),
+				'|' => self.add_token(Pipe),
+				'^' => self.add_token(Caret),
+				'%' => self.add_token(Percent),
 				'{' => self.add_token(Lbrace),
 				'}' => self.add_token(Rbrace),
 				'(' => self.add_token(Lparen),
 				')' => self.add_token(Rparen),
+				'[' => self.add_token(Lbracket),
+				']' => self.add_token(Rbracket),
+				':' => self.add_token(Colon),
+				'.' => {
+					if self.curch_is('.') && self.peek() == Some(&'.') {
+						self.advance();
+						self.advance();
+						self.add_token(Ellipsis);
+					} else {
+						self.add_token(Dot);
+					}
+				}
 				',' => self.add_token(Comma),
 				';' => self.add_token(Semicolon),
 				'#' => self.comment(),
@@ -65,6 +126,9 @@ impl Lexer {
 					if self.curch_is('=') {
 						self.advance();
 						self.add_token(LessEquals);
+					} else if self.curch_is('<') {
+						self.advance();
+						self.add_token(ShiftLeft);
 					} else {
 						self.add_token(LessThan);
 					}
@@ -73,6 +137,9 @@ impl Lexer {
 					if self.curch_is('=') {
 						self.advance();
 						self.add_token(GreaterEquals);
+					} else if self.curch_is('>') {
+						self.advance();
+						self.add_token(ShiftRight);
 					} else {
 						self.add_token(GreaterThan);
 					}
@@ -111,7 +178,7 @@ impl Lexer {
 	fn ident(&mut self) {
 		while let Some(ch) = self.curch() {
 			//if ch == '\n' { self.col += 1};
-			if ch.is_alphabetic() {
+			if ch.is_alphanumeric() || *ch == '_' {
 				self.advance();
 			} else {
 				break;
@@ -138,7 +205,6 @@ impl Lexer {
 		}
 		self.startidx += 1;
 		self.add_token(tk::Comment);
-		self.startidx -= 1;
 	}
 
 	fn string(&mut self) -> Result<(), String> {
@@ -181,8 +247,9 @@ impl Lexer {
 			}
 		}
 
-		let kind = match self.curch() {
-			Some('.') => {
+		// a dot without digits after it is a method call, like `1.repr()`
+		let kind = match (self.curch(), self.peek()) {
+			(Some('.'), Some(next)) if next.is_ascii_digit() => {
 				self.advance();
 				tk::Float
 			}
@@ -2,11 +2,15 @@
 pub(crate) mod tests;
 
 pub(crate) mod ast;
+pub(crate) mod checker;
 pub(crate) mod context;
-pub(crate) mod object;
+pub(crate) mod resolver;
 pub(crate) mod stdlib;
 pub(crate) mod token;
 
+pub mod error;
 pub mod interpreter;
+pub mod iterator;
 pub mod lexer;
+pub mod object;
 pub mod parser;
@@ -1,48 +1,529 @@
 //! Simulates objects that can be used as variables
 
-use crate::{ast::Statement, interpreter::Interpreter};
-use std::fmt::{self, Debug, Display, Formatter};
+use crate::{
+	ast::{AstNode, Param},
+	error::Error,
+	interpreter::Interpreter,
+	iterator::Iter,
+};
+use std::{
+	cell::RefCell,
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	fmt::{self, Debug, Display, Formatter},
+	rc::{Rc, Weak},
+};
 
-pub type NativeFunctionCallback = fn(&Vec<Object>, &mut Interpreter) -> Object;
+/// Variables of a scope, which is shared so modules and their functions can refer to it
+pub type Scope = Rc<RefCell<Variables>>;
+
+#[derive(Debug, Default)]
+pub struct Variables {
+	pub values: HashMap<String, Object>,
+	/// Names declared with `const`, which can't be assigned to
+	pub constants: HashSet<String>,
+}
+
+/// Returning an `Err` throws a runtime error at the call site.
+/// `String` errors convert into an `Error` with `?` or `into`.
+pub type NativeFunctionCallback = fn(&Vec<Object>, &mut Interpreter) -> Result<Object, Error>;
 
 #[derive(Clone, Debug)]
 pub enum Object {
 	Bool(bool),
 	Str(String),
-	Number(f64),
-	Function(Box<FunctionDef>),
-	NativeFunction(Box<NativeFunctionDef>),
+	Int(i64),
+	Float(f64),
+	/// Lists are shared, so every copy refers to the same elements
+	List(Rc<RefCell<Vec<Object>>>),
+	/// String keys to values, kept in the order they were added. Shared like lists
+	Map(Rc<RefCell<MapEntries>>),
+	/// Ints counting from `start` up to, but not including, `end`
+	Range {
+		start: i64,
+		end: i64,
+		step: i64,
+	},
+	/// A struct type, which is called to make instances of it
+	Struct(Rc<StructDef>),
+	/// Instances are shared, so every copy refers to the same fields
+	Instance(Rc<RefCell<Instance>>),
+	/// A lazy, single pass iterator such as the ones made by `map` and `filter`
+	Iterator(Rc<RefCell<Iter>>),
+	Function(Rc<FunctionDef>),
+	NativeFunction(Rc<NativeFunctionDef>),
+	/// An imported file, whose globals are accessed like fields
+	Module(Rc<Module>),
+	/// `ok(value)` or `err(value)`, for returning errors instead of throwing them
+	Outcome {
+		ok: bool,
+		value: Box<Object>,
+	},
 	/// Type that's used to signify no value
-	Unit,
+	Nil,
 }
 
 #[derive(Clone, Debug)]
 pub struct FunctionDef {
 	pub name: String,
-	pub params: Vec<String>,
-	pub body: Vec<Statement>,
+	pub params: Vec<Param>,
+	pub body: Vec<AstNode>,
+	/// The globals of the module it was declared in, which it runs with when called from
+	/// another module. Weak since the function is usually stored in those globals
+	pub globals: Weak<RefCell<Variables>>,
+}
+#[derive(Debug)]
+pub struct StructDef {
+	pub name: String,
+	pub fields: Vec<String>,
+	/// Added to by `impl` blocks, which can come after the struct is declared
+	pub methods: RefCell<HashMap<String, Rc<FunctionDef>>>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+	pub def: Rc<StructDef>,
+	/// values in the same order as the fields of `def`
+	pub fields: Vec<Object>,
+}
+
+impl Instance {
+	pub fn field_index(&self, field: &str) -> Option<usize> {
+		self.def.fields.iter().position(|v| v == field)
+	}
+
+	pub fn get(&self, field: &str) -> Option<&Object> {
+		self.field_index(field).map(|i| &self.fields[i])
+	}
+
+	pub fn method(&self, name: &str) -> Option<Rc<FunctionDef>> {
+		self.def.methods.borrow().get(name).cloned()
+	}
+}
+
+/// The entries of a map in the order they were added, with where each key is in them so
+/// they can be looked up without a scan
+#[derive(Debug, Default)]
+pub struct MapEntries {
+	entries: Vec<(String, Object)>,
+	positions: HashMap<String, usize>,
 }
+
+impl MapEntries {
+	pub fn get(&self, key: &str) -> Option<&Object> {
+		self.positions.get(key).map(|&i| &self.entries[i].1)
+	}
+
+	pub fn contains_key(&self, key: &str) -> bool {
+		self.positions.contains_key(key)
+	}
+
+	/// Replaces the value of a key that's already there without moving it, or adds it last
+	pub fn insert(&mut self, key: String, value: Object) {
+		match self.positions.get(&key) {
+			Some(&i) => self.entries[i].1 = value,
+			None => {
+				self.positions.insert(key.clone(), self.entries.len());
+				self.entries.push((key, value));
+			}
+		}
+	}
+
+	/// Removes the key, keeping the order of the rest, and returns its value if it was there
+	pub fn remove(&mut self, key: &str) -> Option<Object> {
+		let i = self.positions.remove(key)?;
+		for position in self.positions.values_mut() {
+			if *position > i {
+				*position -= 1;
+			}
+		}
+		Some(self.entries.remove(i).1)
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<(String, Object)> {
+		self.entries.iter()
+	}
+}
+
+#[derive(Debug)]
+pub struct Module {
+	/// The path it was imported with
+	pub path: String,
+	pub globals: Scope,
+}
+
 #[derive(Clone)]
 pub struct NativeFunctionDef {
 	pub name: String,
+	/// Checked before the callback is called, so it can rely on getting these arguments
+	pub arity: Arity,
 	pub callback: NativeFunctionCallback,
 }
 
+/// How many arguments a native function takes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+	Exact(usize),
+	/// From the first number up to the second, inclusive
+	Range(usize, usize),
+	/// At least this many
+	Variadic(usize),
+}
+
+impl Arity {
+	pub fn accepts(&self, count: usize) -> bool {
+		match *self {
+			Arity::Exact(n) => count == n,
+			Arity::Range(min, max) => count >= min && count <= max,
+			Arity::Variadic(min) => count >= min,
+		}
+	}
+
+	/// The arity of a builtin method, which gets its receiver as the first argument
+	pub fn without_receiver(self) -> Arity {
+		match self {
+			Arity::Exact(n) => Arity::Exact(n.saturating_sub(1)),
+			Arity::Range(min, max) => Arity::Range(min.saturating_sub(1), max.saturating_sub(1)),
+			Arity::Variadic(min) => Arity::Variadic(min.saturating_sub(1)),
+		}
+	}
+}
+
+/// "1 was given" or "2 were given", for errors about the number of arguments of a call
+pub(crate) fn given(count: usize) -> String {
+	match count {
+		1 => "1 was given".to_string(),
+		_ => format!("{count} were given"),
+	}
+}
+
+impl Display for Arity {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match *self {
+			Arity::Exact(1) => write!(f, "1 argument"),
+			Arity::Exact(n) => write!(f, "{n} arguments"),
+			Arity::Range(min, max) => write!(f, "{min} to {max} arguments"),
+			Arity::Variadic(1) => write!(f, "at least 1 argument"),
+			Arity::Variadic(min) => write!(f, "at least {min} arguments"),
+		}
+	}
+}
+
 impl Debug for NativeFunctionDef {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
 		write!(f, "{}", self.name)
 	}
 }
 
+impl Object {
+	pub fn list(items: Vec<Object>) -> Object {
+		Object::List(Rc::new(RefCell::new(items)))
+	}
+
+	/// A map of `entries`, where a repeated key replaces the value of the earlier one
+	pub fn map(entries: Vec<(String, Object)>) -> Object {
+		let mut map = MapEntries::default();
+		for (k, v) in entries {
+			map.insert(k, v);
+		}
+		Object::Map(Rc::new(RefCell::new(map)))
+	}
+
+	pub fn iterator(iter: Iter) -> Object {
+		Object::Iterator(Rc::new(RefCell::new(iter)))
+	}
+
+	/// Exposes a Rust iterator to scripts, which can loop over it or pass it to `map` etc.
+	pub fn from_iter(iter: impl Iterator<Item = Object> + 'static) -> Object {
+		Object::iterator(Iter::Native(Box::new(iter)))
+	}
+
+	/// Like `to_string`, but strings are quoted so they can't be mistaken for other values
+	pub fn repr(&self) -> String {
+		self.repr_in(&mut vec![])
+	}
+
+	/// `repr` of a value inside the containers in `printing`
+	fn repr_in(&self, printing: &mut Vec<*const ()>) -> String {
+		match self {
+			Object::Str(v) => format!("{v:?}"),
+			_ => self.format(printing),
+		}
+	}
+
+	/// Name of the type, as used in error messages
+	pub fn type_name(&self) -> &'static str {
+		use Object::*;
+		match self {
+			Bool(_) => "bool",
+			Str(_) => "string",
+			Int(_) => "int",
+			Float(_) => "float",
+			List(_) => "list",
+			Map(_) => "map",
+			Range { .. } => "range",
+			Iterator(_) => "iterator",
+			Struct(_) => "struct",
+			Instance(_) => "instance",
+			Function(_) | NativeFunction(_) => "function",
+			Module(_) => "module",
+			Outcome { .. } => "result",
+			Nil => "nil",
+		}
+	}
+}
+
+/// Values of different types are never equal, except ints and floats which compare by value.
+/// Lists, maps and instances compare their contents and functions are only equal to
+/// themselves. Maps are equal when they have the same entries in any order.
+impl PartialEq for Object {
+	fn eq(&self, other: &Object) -> bool {
+		self.equals(other, &mut vec![])
+	}
+}
+
+/// Pairs of containers, left then right, that are being compared further out
+type Comparing = Vec<(*const (), *const ())>;
+
+/// Compares two containers unless they're already being compared further out, in which case
+/// they contain themselves and `revisited` is the result instead of recursing forever
+fn compared<T>(
+	pair: (*const (), *const ()),
+	comparing: &mut Comparing,
+	revisited: T,
+	compare: impl FnOnce(&mut Comparing) -> T,
+) -> T {
+	if comparing.contains(&pair) {
+		return revisited;
+	}
+	comparing.push(pair);
+	let res = compare(comparing);
+	comparing.pop();
+	res
+}
+
+impl Object {
+	/// `==`, where `comparing` holds the containers the values are inside of
+	fn equals(&self, other: &Object, comparing: &mut Comparing) -> bool {
+		use Object::*;
+		match (self, other) {
+			(Int(_) | Float(_), Int(_) | Float(_)) => {
+				self.partial_cmp(other) == Some(Ordering::Equal)
+			}
+			(Bool(l), Bool(r)) => l == r,
+			(Str(l), Str(r)) => l == r,
+			(List(l), List(r)) => {
+				let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+				Rc::ptr_eq(l, r)
+					|| compared(pair, comparing, true, |comparing| {
+						let (l, r) = (l.borrow(), r.borrow());
+						l.len() == r.len()
+							&& l.iter().zip(r.iter()).all(|(l, r)| l.equals(r, comparing))
+					})
+			}
+			(Map(l), Map(r)) => {
+				let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+				Rc::ptr_eq(l, r)
+					|| compared(pair, comparing, true, |comparing| {
+						let (l, r) = (l.borrow(), r.borrow());
+						l.len() == r.len()
+							&& l.iter().all(|(k, v)| match r.get(k) {
+								Some(r) => v.equals(r, comparing),
+								None => false,
+							})
+					})
+			}
+			(
+				Range { start, end, step },
+				Range {
+					start: r_start,
+					end: r_end,
+					step: r_step,
+				},
+			) => (start, end, step) == (r_start, r_end, r_step),
+			(Iterator(l), Iterator(r)) => Rc::ptr_eq(l, r),
+			(Struct(l), Struct(r)) => Rc::ptr_eq(l, r),
+			(Instance(l), Instance(r)) => {
+				let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+				Rc::ptr_eq(l, r)
+					|| compared(pair, comparing, true, |comparing| {
+						let (l, r) = (l.borrow(), r.borrow());
+						Rc::ptr_eq(&l.def, &r.def)
+							&& l.fields
+								.iter()
+								.zip(r.fields.iter())
+								.all(|(l, r)| l.equals(r, comparing))
+					})
+			}
+			(Function(l), Function(r)) => Rc::ptr_eq(l, r),
+			(NativeFunction(l), NativeFunction(r)) => Rc::ptr_eq(l, r),
+			(Module(l), Module(r)) => Rc::ptr_eq(l, r),
+			(
+				Outcome { ok, value },
+				Outcome {
+					ok: r_ok,
+					value: r_value,
+				},
+			) => ok == r_ok && value.equals(r_value, comparing),
+			(Nil, Nil) => true,
+			_ => false,
+		}
+	}
+}
+
+/// Only numbers, strings and lists have an order. Strings are ordered lexicographically
+/// and lists by their first differing element, then by length. Lists that contain
+/// themselves have no order once the comparison comes back around to them.
+impl PartialOrd for Object {
+	fn partial_cmp(&self, other: &Object) -> Option<Ordering> {
+		self.compare(other, &mut vec![])
+	}
+}
+
+impl Object {
+	/// `partial_cmp`, where `comparing` holds the lists the values are inside of
+	fn compare(&self, other: &Object, comparing: &mut Comparing) -> Option<Ordering> {
+		use Object::*;
+		match (self, other) {
+			(Int(l), Int(r)) => Some(l.cmp(r)),
+			(Float(l), Float(r)) => l.partial_cmp(r),
+			(Int(l), Float(r)) => compare_int_float(*l, *r),
+			(Float(l), Int(r)) => compare_int_float(*r, *l).map(Ordering::reverse),
+			(Str(l), Str(r)) => Some(l.cmp(r)),
+			(List(l), List(r)) => {
+				let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+				compared(pair, comparing, None, |comparing| {
+					let (l, r) = (l.borrow(), r.borrow());
+					for (l, r) in l.iter().zip(r.iter()) {
+						match l.compare(r, comparing)? {
+							Ordering::Equal => continue,
+							ord => return Some(ord),
+						}
+					}
+					Some(l.len().cmp(&r.len()))
+				})
+			}
+			_ => None,
+		}
+	}
+}
+
+/// Compares without converting the int to a float, which would lose precision for big ints
+fn compare_int_float(int: i64, float: f64) -> Option<Ordering> {
+	// 2^63 is the first float past i64::MAX
+	const LIMIT: f64 = 9223372036854775808.0;
+
+	if float.is_nan() {
+		return None;
+	}
+	if float >= LIMIT {
+		return Some(Ordering::Less);
+	}
+	if float < -LIMIT {
+		return Some(Ordering::Greater);
+	}
+
+	let whole = float.trunc();
+	match int.cmp(&(whole as i64)) {
+		Ordering::Equal => 0.0.partial_cmp(&(float - whole)),
+		ord => Some(ord),
+	}
+}
+
 impl Display for Object {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(f, "{}", self.format(&mut vec![]))
+	}
+}
+
+/// Writes a container unless it's already being written further out, in which case it
+/// contains itself and `placeholder` is written instead of recursing forever
+fn guarded(
+	container: *const (),
+	printing: &mut Vec<*const ()>,
+	placeholder: &str,
+	write: impl FnOnce(&mut Vec<*const ()>) -> String,
+) -> String {
+	if printing.contains(&container) {
+		return placeholder.to_string();
+	}
+	printing.push(container);
+	let res = write(printing);
+	printing.pop();
+	res
+}
+
+impl Object {
+	/// `to_string`, where `printing` holds the containers the value is inside of
+	fn format(&self, printing: &mut Vec<*const ()>) -> String {
 		use Object::*;
-		let res = match self {
+		match self {
 			Bool(v) => v.to_string(),
-			Number(v) => v.to_string(),
+			Int(v) => v.to_string(),
+			Float(v) => {
+				// whole floats keep their decimal point so they can be told apart from ints
+				let text = v.to_string();
+				if v.is_finite() && !text.contains('.') {
+					format!("{text}.0")
+				} else {
+					text
+				}
+			}
 			Str(v) => v.clone(),
-			_ => todo!(),
-		};
-		write!(f, "{}", res)
+			List(v) => guarded(Rc::as_ptr(v) as *const (), printing, "[...]", |printing| {
+				let items: Vec<String> = v.borrow().iter().map(|v| v.repr_in(printing)).collect();
+				format!("[{}]", items.join(", "))
+			}),
+			Map(v) => guarded(Rc::as_ptr(v) as *const (), printing, "{...}", |printing| {
+				let entries: Vec<String> = v
+					.borrow()
+					.iter()
+					.map(|(k, v)| format!("{k:?}: {}", v.repr_in(printing)))
+					.collect();
+				format!("{{{}}}", entries.join(", "))
+			}),
+			Range { start, end, step } => format!("range({start}, {end}, {step})"),
+			Iterator(_) => "<iterator>".to_string(),
+			Struct(v) => format!("<struct {}>", v.name),
+			Instance(instance) => {
+				let v = instance.borrow();
+				let placeholder = format!("{} {{ ... }}", v.def.name);
+				guarded(
+					Rc::as_ptr(instance) as *const (),
+					printing,
+					&placeholder,
+					|printing| {
+						let fields: Vec<String> = v
+							.def
+							.fields
+							.iter()
+							.zip(v.fields.iter())
+							.map(|(name, value)| format!("{name}: {}", value.repr_in(printing)))
+							.collect();
+						format!("{} {{ {} }}", v.def.name, fields.join(", "))
+					},
+				)
+			}
+			Function(v) => {
+				let params: Vec<String> = v
+					.params
+					.iter()
+					.map(|v| match v.rest {
+						true => format!("...{}", v.name),
+						false => v.name.clone(),
+					})
+					.collect();
+				format!("<fn {}({})>", v.name, params.join(", "))
+			}
+			NativeFunction(v) => format!("<native {}>", v.name),
+			Module(v) => format!("<module {}>", v.path),
+			Outcome { ok: true, value } => format!("ok({})", value.repr()),
+			Outcome { ok: false, value } => format!("err({})", value.repr()),
+			Nil => "nil".to_string(),
+		}
 	}
 }
@@ -1,19 +1,22 @@
 //! Recursive descent based parser
 
 use crate::{ast::*, token::TokenKind as tk, token::*};
-use std::{fmt::Display, iter::Peekable, vec::IntoIter};
+use std::fmt::Display;
 
 #[derive(Debug)]
 pub struct Parser {
 	curtok: Token,
-	iter: Peekable<IntoIter<Token>>,
+	tokens: Vec<Token>,
+	/// index of the token after `curtok`
+	pos: usize,
 }
 
 impl Parser {
 	pub fn new(tokens: Vec<Token>) -> Self {
 		Parser {
 			curtok: tokens[0].clone(),
-			iter: tokens.into_iter().peekable(),
+			tokens: tokens,
+			pos: 0,
 		}
 	}
 
@@ -35,9 +38,18 @@ impl Parser {
 
 		let matching_token = self.curtok.clone();
 		match self.curtok.kind {
-			Var => program.push((self.parse_var()?, matching_token)),
+			Var | Const => program.push((self.parse_var()?, matching_token)),
 			Fn => program.push((self.parse_function()?, matching_token)),
 			While => program.push((self.parse_while()?, matching_token)),
+			For => program.push((self.parse_for()?, matching_token)),
+			Struct => program.push((self.parse_struct()?, matching_token)),
+			Impl => program.push((self.parse_impl()?, matching_token)),
+			Import => program.push((self.parse_import()?, matching_token)),
+			Identifier if self.at_from_import() => {
+				program.push((self.parse_import()?, matching_token))
+			}
+			Throw => program.push((self.parse_throw()?, matching_token)),
+			Try => program.push((self.parse_try()?, matching_token)),
 			Lbrace => program.push((Block(self.parse_block()?), matching_token)),
 			Semicolon | Comment => drop(self.next()),
 			_ => program.push((
@@ -56,11 +68,19 @@ impl Parser {
 		use tk::*;
 
 		let mut skip = true;
+		let start = self.curtok.clone();
 
 		let mut left = match self.curtok.kind {
-			Integer | Float => {
-				let clone = self.curtok.clone();
-				Expression::Number(clone.text.parse().unwrap())
+			Integer => self.parse_int(&self.curtok.text)?,
+			Float => Expression::Float(self.curtok.text.parse().unwrap()),
+			// `Name { field:` starts a struct literal, anything else leaves the brace for a block
+			Identifier
+				if self.peek_is(1, Lbrace)
+					&& self.peek_is(2, Identifier)
+					&& self.peek_is(3, Colon) =>
+			{
+				skip = false;
+				self.parse_struct_literal()?
 			}
 			Identifier => Expression::Identifier(self.curtok.text.clone()),
 			Lparen => {
@@ -69,20 +89,35 @@ impl Parser {
 				self.parse_expression(Precedence::Iota)?
 			}
 			String => Expression::Str(self.curtok.text.clone()),
+			Lbracket => {
+				self.next();
+				skip = false;
+
+				Expression::List(self.parse_delimited(Rbracket)?)
+			}
 			True => Expression::Bool(true),
+			Nil => Expression::Nil,
 			False => Expression::Bool(false),
 			Minus => {
+				let token = self.curtok.clone();
 				self.next();
 				skip = false;
 
-				Expression::BinOp(
-					Box::new(Expression::Number(-1.0)),
-					Star,
-					Box::new(self.parse_expression(Precedence::Prefix)?),
-				)
+				// the minus is folded into an int literal so the smallest int can be written,
+				// unless the literal is part of something that binds tighter, like `-2 ** 2`
+				let tighter = match self.tokens.get(self.pos) {
+					Some(v) => Precedence::of_token(v) > Precedence::Prefix,
+					None => false,
+				};
+				if self.curtok_is(Integer) && !tighter {
+					let literal = self.parse_int(&format!("-{}", self.curtok.text))?;
+					self.next();
+					literal
+				} else {
+					Expression::Unary(token, Box::new(self.parse_expression(Precedence::Prefix)?))
+				}
 			}
 			_ => {
-				dbg!(&self);
 				return Err(self.error(format!(
 					"Expected an expression. Instead got {:?}",
 					self.curtok.kind
@@ -95,7 +130,7 @@ impl Parser {
 		}
 
 		while !self.curtok_is(EOF) && precedence < Precedence::of_token(&self.curtok) {
-			if let Some(expression) = self.parse_postfix_expression(&left)? {
+			if let Some(expression) = self.parse_postfix_expression(&left, &start)? {
 				left = expression;
 			} else if let Some(expression) = self.parse_infix_expression(&left)? {
 				left = expression;
@@ -111,6 +146,13 @@ impl Parser {
 		return Ok(left);
 	}
 
+	fn parse_int(&self, text: &str) -> Result<Expression, String> {
+		match text.parse() {
+			Ok(v) => Ok(Expression::Int(v)),
+			Err(_) => Err(self.error(format!("Integer {text} doesn't fit in 64 bits"))),
+		}
+	}
+
 	fn parse_while(&mut self) -> Result<Statement, String> {
 		self.expect_kind(TokenKind::While)?;
 		self.next();
@@ -125,6 +167,118 @@ impl Parser {
 		});
 	}
 
+	fn parse_for(&mut self) -> Result<Statement, String> {
+		self.expect_kind(TokenKind::For)?;
+		self.next();
+
+		let variable = self.expect_kind(TokenKind::Identifier)?;
+		self.next();
+
+		self.expect_kind(TokenKind::In)?;
+		self.next();
+
+		let iterable = self.parse_expression(Precedence::Iota)?;
+
+		let body = self.parse_block()?;
+
+		return Ok(Statement::For {
+			variable: variable.text,
+			iterable: iterable,
+			body: body,
+		});
+	}
+
+	fn parse_struct(&mut self) -> Result<Statement, String> {
+		self.expect_kind(TokenKind::Struct)?;
+		self.next();
+
+		let iden = self.expect_kind(TokenKind::Identifier)?;
+		self.next();
+
+		self.expect_kind(TokenKind::Lbrace)?;
+		self.next();
+
+		let mut fields = Vec::new();
+
+		while !self.curtok_is(TokenKind::Rbrace) {
+			let field = self.expect_kind(TokenKind::Identifier)?;
+			if fields.contains(&field.text) {
+				return Err(self.error(format!("Field `{}` is declared twice", field.text)));
+			}
+			fields.push(field.text);
+			self.next();
+
+			if self.curtok_is(TokenKind::Comma) {
+				self.next();
+			}
+		}
+
+		self.next();
+
+		Ok(Statement::StructDeclaration {
+			name: iden.text,
+			fields: fields,
+		})
+	}
+
+	fn parse_impl(&mut self) -> Result<Statement, String> {
+		self.expect_kind(TokenKind::Impl)?;
+		self.next();
+
+		let iden = self.expect_kind(TokenKind::Identifier)?;
+		self.next();
+
+		self.expect_kind(TokenKind::Lbrace)?;
+		self.next();
+
+		let mut methods = Vec::new();
+
+		while !self.curtok_is(TokenKind::Rbrace) {
+			match self.curtok.kind {
+				TokenKind::Comment | TokenKind::Semicolon => drop(self.next()),
+				_ => methods.push(self.parse_function()?),
+			}
+		}
+
+		self.next();
+
+		Ok(Statement::Impl {
+			name: iden.text,
+			methods: methods,
+		})
+	}
+
+	fn parse_struct_literal(&mut self) -> Result<Expression, String> {
+		let iden = self.expect_kind(TokenKind::Identifier)?;
+		self.next();
+
+		self.expect_kind(TokenKind::Lbrace)?;
+		self.next();
+
+		let mut fields = Vec::new();
+
+		while !self.curtok_is(TokenKind::Rbrace) {
+			let field = self.expect_kind(TokenKind::Identifier)?;
+			self.next();
+
+			self.expect_kind(TokenKind::Colon)?;
+			self.next();
+
+			fields.push((field.text, self.parse_expression(Precedence::Iota)?));
+
+			if self.curtok_is(TokenKind::Comma) {
+				self.next();
+			}
+		}
+
+		self.next();
+
+		Ok(Expression::StructLiteral {
+			name: iden.text,
+			fields: fields,
+		})
+	}
+
 	fn parse_function(&mut self) -> Result<Statement, String> {
 		// expect fn keyword (just in case)
 		self.expect_kind(TokenKind::Fn)?;
@@ -140,37 +294,93 @@ impl Parser {
 		let mut params = Vec::new();
 
 		while !self.curtok_is(TokenKind::Rparen) {
-			if self.curtok_is(TokenKind::Comma) {
-				let _param = self.expect_peek(TokenKind::Identifier)?;
+			if params.last().is_some_and(|v: &Param| v.rest) {
+				return Err(self.error("The rest parameter has to be the last one"));
+			}
+
+			let rest = self.curtok_is(TokenKind::Ellipsis);
+			if rest {
 				self.next();
 			}
 
-			if self.curtok_is(TokenKind::Identifier) {
-				params.push(self.curtok.text.clone());
+			let param = self.expect_kind(TokenKind::Identifier)?;
+			self.next();
+
+			let annotation = self.parse_annotation()?;
+
+			let mut default = None;
+			if !rest && self.curtok_is(TokenKind::Assign) {
 				self.next();
+				default = Some(self.parse_expression(Precedence::Iota)?);
 			}
-		}
 
-		//expect function body
+			params.push(Param {
+				name: param.text,
+				annotation: annotation,
+				default: default,
+				rest: rest,
+			});
+
+			if self.curtok_is(TokenKind::Comma) {
+				self.next();
+			} else {
+				self.expect_kind(TokenKind::Rparen)?;
+			}
+		}
 		self.next();
 
+		let mut return_type = None;
+		if self.curtok_is(TokenKind::Arrow) {
+			self.next();
+			return_type = Some(self.parse_type()?);
+		}
+
+		//expect function body
 		let body = self.parse_block()?;
 
 		Ok(Statement::FunctionDeclaration {
 			name: iden.text,
 			body: body,
 			params: params,
+			return_type: return_type,
 		})
 	}
 
+	/// Parses the `: type` after a name if there is one
+	fn parse_annotation(&mut self) -> Result<Option<Type>, String> {
+		if !self.curtok_is(TokenKind::Colon) {
+			return Ok(None);
+		}
+		self.next();
+
+		return Ok(Some(self.parse_type()?));
+	}
+
+	fn parse_type(&mut self) -> Result<Type, String> {
+		let res = match self.curtok.kind {
+			// nil is a keyword, so it's not an identifier like the other type names
+			TokenKind::Nil => Type::Nil,
+			_ => Type::from_name(&self.expect_kind(TokenKind::Identifier)?.text),
+		};
+		self.next();
+
+		return Ok(res);
+	}
+
+	/// Parses a var or const declaration
 	fn parse_var(&mut self) -> Result<Statement, String> {
 		// sanity check
-		self.expect_kind(TokenKind::Var)?;
+		let constant = self.curtok_is(TokenKind::Const);
+		if !constant {
+			self.expect_kind(TokenKind::Var)?;
+		}
 		self.next();
 		// expect identifier
 		let iden = self.expect_kind(TokenKind::Identifier)?;
 		self.next();
 
+		let annotation = self.parse_annotation()?;
+
 		// expect assignment
 		self.expect_kind(TokenKind::Assign)?;
 		self.next();
@@ -178,23 +388,32 @@ impl Parser {
 		// parse expression
 		let expr = self.parse_expression(Precedence::Iota)?;
 
-		let res = Statement::VarDeclaration {
-			name: iden.text,
-			value: expr,
+		let res = match constant {
+			true => Statement::ConstDeclaration {
+				name: iden.text,
+				annotation: annotation,
+				value: expr,
+			},
+			false => Statement::VarDeclaration {
+				name: iden.text,
+				annotation: annotation,
+				value: expr,
+			},
 		};
 
 		return Ok(res);
 	}
 
 	/// Parses the areas between {}
-	fn parse_block(&mut self) -> Result<Vec<Statement>, String> {
+	fn parse_block(&mut self) -> Result<Vec<AstNode>, String> {
 		self.expect_kind(TokenKind::Lbrace)?;
 		self.next();
 
 		let mut block = vec![];
 
 		while self.curtok.kind != TokenKind::Rbrace {
-			block.push(self.parse_statement()?);
+			let matching_token = self.curtok.clone();
+			block.push((self.parse_statement()?, matching_token));
 		}
 
 		self.next();
@@ -205,9 +424,16 @@ impl Parser {
 	fn parse_statement(&mut self) -> Result<Statement, String> {
 		use tk::*;
 		match self.curtok.kind {
-			Var => self.parse_var(),
+			Var | Const => self.parse_var(),
 			Return => self.parse_return(),
 			While => self.parse_while(),
+			For => self.parse_for(),
+			Struct => self.parse_struct(),
+			Impl => self.parse_impl(),
+			Import => self.parse_import(),
+			Identifier if self.at_from_import() => self.parse_import(),
+			Throw => self.parse_throw(),
+			Try => self.parse_try(),
 			Lbrace => Ok(Statement::Block(self.parse_block()?)),
 			_ => Ok(Statement::Expression(
 				self.parse_expression(Precedence::Iota)?,
@@ -225,30 +451,159 @@ impl Parser {
 		return Ok(res);
 	}
 
+	/// `from` and `as` aren't keywords, so they can still be used as names elsewhere
+	fn at_from_import(&self) -> bool {
+		self.curtok.text == "from" && self.peek_is(1, TokenKind::String)
+	}
+
+	/// Parses `import "path" as name` or `from "path" import a, b`
+	fn parse_import(&mut self) -> Result<Statement, String> {
+		let selective = self.curtok_is(TokenKind::Identifier);
+		self.next();
+
+		let path = self.expect_kind(TokenKind::String)?.text;
+		self.next();
+
+		if !selective {
+			if self.curtok.text != "as" {
+				return Err(self.error(format!(
+					"Expected `as` after the import path. Instead got {:?}",
+					self.curtok.kind
+				)));
+			}
+			self.next();
+
+			let name = self.expect_kind(TokenKind::Identifier)?;
+			self.next();
+
+			return Ok(Statement::Import {
+				path: path,
+				name: name.text,
+			});
+		}
+
+		self.expect_kind(TokenKind::Import)?;
+		self.next();
+
+		let mut names = vec![self.expect_kind(TokenKind::Identifier)?.text];
+		self.next();
+
+		while self.curtok_is(TokenKind::Comma) {
+			self.next();
+			names.push(self.expect_kind(TokenKind::Identifier)?.text);
+			self.next();
+		}
+
+		return Ok(Statement::FromImport {
+			path: path,
+			names: names,
+		});
+	}
+
+	fn parse_throw(&mut self) -> Result<Statement, String> {
+		self.expect_kind(TokenKind::Throw)?;
+		self.next();
+
+		return Ok(Statement::Throw(self.parse_expression(Precedence::Iota)?));
+	}
+
+	fn parse_try(&mut self) -> Result<Statement, String> {
+		self.expect_kind(TokenKind::Try)?;
+		self.next();
+
+		let body = self.parse_block()?;
+
+		let mut catch = None;
+		if self.curtok_is(TokenKind::Catch) {
+			self.next();
+
+			let name = self.expect_kind(TokenKind::Identifier)?;
+			self.next();
+
+			catch = Some((name.text, self.parse_block()?));
+		}
+
+		let mut finally = None;
+		if self.curtok_is(TokenKind::Finally) {
+			self.next();
+			finally = Some(self.parse_block()?);
+		}
+
+		if catch.is_none() && finally.is_none() {
+			return Err(self.error("Expected catch or finally after a try block"));
+		}
+
+		return Ok(Statement::Try {
+			body: body,
+			catch: catch,
+			finally: finally,
+		});
+	}
+
 	fn parse_infix_expression(&mut self, left: &Expression) -> Result<Option<Expression>, String> {
 		use tk::*;
 		match self.curtok.kind {
-			Slash | Star | Minus | Plus | Equals | NotEquals | LessThan | GreaterThan
-			| GreaterEquals | LessEquals => {
+			Slash | Star | Percent | StarStar | Minus | Plus | Equals | NotEquals | LessThan
+			| GreaterThan | GreaterEquals | LessEquals | Ampersand | Pipe | Caret | ShiftLeft
+			| ShiftRight => {
 				let token = self.curtok.clone();
 
 				self.next();
 
-				let right = self.parse_expression(Precedence::of_token(&token))?;
+				// exponents are right associative, so the right side may contain more of them
+				let precedence = match token.kind {
+					StarStar => Precedence::Prefix,
+					_ => Precedence::of_token(&token),
+				};
+
+				let right = self.parse_expression(precedence)?;
 
 				return Ok(Some(Expression::BinOp(
 					Box::new(left.clone()),
-					token.kind,
+					token,
 					Box::new(right),
 				)));
 			}
 			Assign => {
+				let token = self.curtok.clone();
 				self.next();
 
 				let right = self.parse_expression(Precedence::Iota)?;
 
 				Ok(Some(Expression::Assign(
 					Box::new(left.clone()),
+					token,
+					Box::new(right),
+				)))
+			}
+			Coalesce => {
+				self.next();
+
+				let right = self.parse_expression(Precedence::Coalesce)?;
+
+				Ok(Some(Expression::Coalesce(
+					Box::new(left.clone()),
+					Box::new(right),
+				)))
+			}
+			PlusAssign | MinusAssign | StarAssign | SlashAssign => {
+				let kind = match self.curtok.kind {
+					PlusAssign => Plus,
+					MinusAssign => Minus,
+					StarAssign => Star,
+					_ => Slash,
+				};
+				let op = Token {
+					kind: kind,
+					..self.curtok.clone()
+				};
+				self.next();
+
+				let right = self.parse_expression(Precedence::Iota)?;
+
+				Ok(Some(Expression::CompoundAssign(
+					Box::new(left.clone()),
+					op,
 					Box::new(right),
 				)))
 			}
@@ -256,59 +611,176 @@ impl Parser {
 		}
 	}
 
+	/// `start` is the first token of `left`
 	fn parse_postfix_expression(
 		&mut self,
 		left: &Expression,
+		start: &Token,
 	) -> Result<Option<Expression>, String> {
 		use tk::*;
 		match self.curtok.kind {
 			Lparen => {
-				// parse a possibly delimited list
 				self.next();
 
-				let mut args = Vec::new();
+				let (args, named) = self.parse_arguments()?;
 
-				while !self.curtok_is(Rparen) {
-					args.push(self.parse_expression(Precedence::Iota)?);
+				if let Expression::Identifier(v) = left {
+					let res = Expression::Call {
+						name: v.clone(),
+						token: start.clone(),
+						args: args,
+						named: named,
+					};
 
-					if self.curtok.kind == Comma {
-						self.next();
-					}
+					return Ok(Some(res));
 				}
 
-				self.expect_kind(Rparen)?;
+				Ok(Some(Expression::CallValue {
+					callee: Box::new(left.clone()),
+					args: args,
+					named: named,
+				}))
+			}
+			Dot | QuestionDot => {
+				let optional = self.curtok_is(QuestionDot);
+				self.next();
 
+				let field = self.expect_kind(Identifier)?;
 				self.next();
 
-				if let Expression::Identifier(v) = left {
-					let res = Expression::Call {
-						name: v.clone(),
+				if self.curtok_is(Lparen) {
+					self.next();
+
+					let (args, named) = self.parse_arguments()?;
+					return Ok(Some(Expression::MethodCall {
+						object: Box::new(left.clone()),
+						method: field.text,
 						args: args,
+						named: named,
+						optional: optional,
+					}));
+				}
+
+				Ok(Some(Expression::Get {
+					object: Box::new(left.clone()),
+					field: field.text,
+					optional: optional,
+				}))
+			}
+			Question => {
+				self.next();
+				Ok(Some(Expression::Try(Box::new(left.clone()))))
+			}
+			Lbracket => {
+				self.next();
+
+				let start = match self.curtok.kind {
+					Colon => None,
+					_ => Some(Box::new(self.parse_expression(Precedence::Iota)?)),
+				};
+
+				// a colon turns the index into a slice: target[start:end]
+				if self.curtok_is(Colon) {
+					self.next();
+
+					let end = match self.curtok.kind {
+						Rbracket => None,
+						_ => Some(Box::new(self.parse_expression(Precedence::Iota)?)),
 					};
 
-					return Ok(Some(res));
+					self.expect_kind(Rbracket)?;
+					self.next();
+
+					return Ok(Some(Expression::Slice {
+						target: Box::new(left.clone()),
+						start: start,
+						end: end,
+					}));
 				}
 
-				dbg!(left);
-				unreachable!();
+				self.expect_kind(Rbracket)?;
+				self.next();
+
+				Ok(Some(Expression::Index(
+					Box::new(left.clone()),
+					start.unwrap(),
+				)))
 			}
 			_ => Ok(None),
 		}
 	}
 
+	/// Parses the arguments of a call up to and including the `)`, where `name: value`
+	/// arguments are named and come after the positional ones
+	fn parse_arguments(&mut self) -> Result<(Vec<Expression>, Vec<(String, Expression)>), String> {
+		let mut args = Vec::new();
+		let mut named = Vec::new();
+
+		while !self.curtok_is(TokenKind::Rparen) {
+			if self.curtok_is(TokenKind::Identifier) && self.peek_is(1, TokenKind::Colon) {
+				let name = self.curtok.text.clone();
+				self.next();
+				self.next();
+
+				named.push((name, self.parse_expression(Precedence::Iota)?));
+			} else if named.is_empty() {
+				args.push(self.parse_expression(Precedence::Iota)?);
+			} else {
+				return Err(self.error("Positional arguments can't come after named ones"));
+			}
+
+			if self.curtok_is(TokenKind::Comma) {
+				self.next();
+			} else {
+				self.expect_kind(TokenKind::Rparen)?;
+			}
+		}
+
+		self.next();
+
+		return Ok((args, named));
+	}
+
+	/// Parses a comma separated list of expressions up to and including the `end` token
+	fn parse_delimited(&mut self, end: TokenKind) -> Result<Vec<Expression>, String> {
+		let mut list = Vec::new();
+
+		while !self.curtok_is(end.clone()) {
+			list.push(self.parse_expression(Precedence::Iota)?);
+
+			if self.curtok_is(TokenKind::Comma) {
+				self.next();
+			}
+		}
+
+		self.expect_kind(end)?;
+		self.next();
+
+		return Ok(list);
+	}
+
 	fn peek(&mut self) -> Result<Token, String> {
-		if let Some(t) = self.iter.peek() {
+		if let Some(t) = self.tokens.get(self.pos) {
 			return Ok(t.clone());
 		}
 
 		Err(self.error("Parsed past EOF"))
 	}
 
+	/// Checks the kind of the token `n` places after the current one, without consuming anything
+	fn peek_is(&self, n: usize, kind: TokenKind) -> bool {
+		match self.tokens.get(self.pos + n - 1) {
+			Some(t) => t.kind == kind,
+			None => false,
+		}
+	}
+
 	fn next(&mut self) -> Option<Token> {
-		match self.iter.next() {
+		match self.tokens.get(self.pos) {
 			Some(v) => {
+				self.pos += 1;
 				self.curtok = v.clone();
-				return Some(v);
+				return Some(v.clone());
 			}
 			None => None,
 		}
@@ -351,11 +823,17 @@ enum Precedence {
 	Iota,
 	Statement,
 	Assign,
+	Coalesce,
 	LessThanGreaterThan,
 	Equals,
+	BitOr,
+	BitXor,
+	BitAnd,
+	Shift,
 	Sum,
 	Product,
 	Prefix,
+	Exponent,
 	/// The highest precedence
 	Call,
 }
@@ -366,12 +844,18 @@ impl Precedence {
 		use Precedence as prec;
 
 		match tok.kind {
-			Lparen => prec::Call,
-			Slash | Star => prec::Product,
+			Lparen | Lbracket | Dot | QuestionDot | Question => prec::Call,
+			Coalesce => prec::Coalesce,
+			StarStar => prec::Exponent,
+			Slash | Star | Percent => prec::Product,
 			Plus | Minus => prec::Sum,
 			Equals | NotEquals => prec::Equals,
 			LessThan | GreaterThan | LessEquals | GreaterEquals => prec::LessThanGreaterThan,
-			Assign => prec::Assign,
+			Pipe => prec::BitOr,
+			Caret => prec::BitXor,
+			Ampersand => prec::BitAnd,
+			ShiftLeft | ShiftRight => prec::Shift,
+			Assign | PlusAssign | MinusAssign | StarAssign | SlashAssign => prec::Assign,
 			Semicolon => prec::Statement,
 			_ => prec::Iota,
 		}
@@ -0,0 +1,208 @@
+//! Checks a program before it runs for mistakes that can be found without running it.
+//! Currently that's assigning to a constant and declaring a name twice in the same scope,
+//! which the interpreter also checks for the cases that can't be seen here.
+
+use crate::{ast::*, token::Token};
+use std::collections::HashMap;
+
+struct Resolver {
+	/// Declared names and whether they're constant, innermost scope last
+	scopes: Vec<HashMap<String, bool>>,
+	/// The top level statement being checked, which errors are positioned at
+	curtok: Token,
+}
+
+pub fn check(program: &[AstNode]) -> Result<(), String> {
+	let mut resolver = Resolver {
+		scopes: vec![HashMap::new()],
+		curtok: match program.first() {
+			Some((_, token)) => token.clone(),
+			None => return Ok(()),
+		},
+	};
+
+	for (statement, token) in program {
+		resolver.curtok = token.clone();
+		resolver.statement(statement)?;
+	}
+	Ok(())
+}
+
+impl Resolver {
+	fn statement(&mut self, statement: &Statement) -> Result<(), String> {
+		match statement {
+			Statement::Return(expr) | Statement::Expression(expr) | Statement::Throw(expr) => {
+				self.expression(expr)?;
+			}
+			Statement::VarDeclaration { name, value, .. } => {
+				self.expression(value)?;
+				self.declare(name, false)?;
+			}
+			Statement::ConstDeclaration { name, value, .. } => {
+				self.expression(value)?;
+				self.declare(name, true)?;
+			}
+			Statement::FunctionDeclaration {
+				name, params, body, ..
+			} => {
+				self.declare(name, false)?;
+				self.function(params, body)?;
+			}
+			Statement::StructDeclaration { name, .. } => self.declare(name, false)?,
+			Statement::Impl { methods, .. } => {
+				for method in methods {
+					if let Statement::FunctionDeclaration { params, body, .. } = method {
+						self.function(params, body)?;
+					}
+				}
+			}
+			Statement::Import { name, .. } => self.declare(name, false)?,
+			Statement::FromImport { names, .. } => {
+				for name in names {
+					self.declare(name, false)?;
+				}
+			}
+			Statement::While { condition, body } => {
+				self.expression(condition)?;
+				self.block(&[], body)?;
+			}
+			Statement::For {
+				variable,
+				iterable,
+				body,
+			} => {
+				self.expression(iterable)?;
+				self.block(&[variable.clone()], body)?;
+			}
+			Statement::Block(body) => self.block(&[], body)?,
+			Statement::Try {
+				body,
+				catch,
+				finally,
+			} => {
+				self.block(&[], body)?;
+				if let Some((name, handler)) = catch {
+					// the caught value is in a scope of its own around the handler's block
+					self.scopes.push(HashMap::from([(name.clone(), false)]));
+					let res = self.block(&[], handler);
+					self.scopes.pop();
+					res?;
+				}
+				if let Some(finally) = finally {
+					self.block(&[], finally)?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Checks statements in a new scope that starts with the variables `names`
+	fn block(&mut self, names: &[String], body: &Vec<AstNode>) -> Result<(), String> {
+		self.scopes
+			.push(names.iter().map(|v| (v.clone(), false)).collect());
+
+		let res = body.iter().try_for_each(|(v, _)| self.statement(v));
+
+		self.scopes.pop();
+		res
+	}
+
+	fn function(&mut self, params: &[Param], body: &Vec<AstNode>) -> Result<(), String> {
+		for default in params.iter().filter_map(|v| v.default.as_ref()) {
+			self.expression(default)?;
+		}
+		let names: Vec<String> = params.iter().map(|v| v.name.clone()).collect();
+		self.block(&names, body)
+	}
+
+	fn declare(&mut self, name: &String, constant: bool) -> Result<(), String> {
+		let scope = self.scopes.last_mut().unwrap();
+		if scope.insert(name.clone(), constant).is_some() {
+			return Err(self.error(format!("`{name}` is already declared in this scope")));
+		}
+		Ok(())
+	}
+
+	fn expression(&mut self, expr: &Expression) -> Result<(), String> {
+		match expr {
+			Expression::Bool(_)
+			| Expression::Nil
+			| Expression::Int(_)
+			| Expression::Float(_)
+			| Expression::Str(_)
+			| Expression::Identifier(_) => {}
+			Expression::Assign(target, token, value)
+			| Expression::CompoundAssign(target, token, value) => {
+				if let Expression::Identifier(name) = &**target {
+					// the innermost declaration is the one that's assigned to
+					let constant = self.scopes.iter().rev().find_map(|v| v.get(name));
+					if constant == Some(&true) {
+						let message = format!("Can't assign to constant `{name}`");
+						return Err(self.error_at(token, message));
+					}
+				}
+				self.expression(target)?;
+				self.expression(value)?;
+			}
+			Expression::List(items) => {
+				for item in items {
+					self.expression(item)?;
+				}
+			}
+			Expression::Call { args, named, .. } => {
+				for arg in args.iter().chain(named.iter().map(|(_, v)| v)) {
+					self.expression(arg)?;
+				}
+			}
+			Expression::CallValue {
+				callee,
+				args,
+				named,
+			} => {
+				self.expression(callee)?;
+				for arg in args.iter().chain(named.iter().map(|(_, v)| v)) {
+					self.expression(arg)?;
+				}
+			}
+			Expression::StructLiteral { fields, .. } => {
+				for (_, value) in fields {
+					self.expression(value)?;
+				}
+			}
+			Expression::Get { object, .. } => self.expression(object)?,
+			Expression::MethodCall {
+				object,
+				args,
+				named,
+				..
+			} => {
+				self.expression(object)?;
+				for arg in args.iter().chain(named.iter().map(|(_, v)| v)) {
+					self.expression(arg)?;
+				}
+			}
+			Expression::Try(expr) | Expression::Unary(_, expr) => self.expression(expr)?,
+			Expression::Coalesce(left, right)
+			| Expression::Index(left, right)
+			| Expression::BinOp(left, _, right) => {
+				self.expression(left)?;
+				self.expression(right)?;
+			}
+			Expression::Slice { target, start, end } => {
+				self.expression(target)?;
+				for bound in [start, end].into_iter().flatten() {
+					self.expression(bound)?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn error(&self, text: String) -> String {
+		self.error_at(&self.curtok, text)
+	}
+
+	fn error_at(&self, token: &Token, text: String) -> String {
+		format!("(Ln {}, Col {}) {}", token.ln, token.col, text)
+	}
+}
@@ -1,7 +1,24 @@
 //! Used to set the functions that are usable by the interpreter
 #![allow(non_upper_case_globals)]
-use crate::object::{NativeFunctionCallback, NativeFunctionDef, Object, Object::*};
-use std::collections::HashMap;
+use crate::object::{
+	Arity, NativeFunctionCallback, NativeFunctionDef, Object, Object::*, Variables,
+};
+use std::{cell::Ref, rc::Rc};
+
+pub(crate) mod fs;
+pub(crate) mod input;
+mod iter;
+mod json;
+mod map;
+mod math;
+mod process;
+pub(crate) mod random;
+mod result;
+mod string;
+pub(crate) mod time;
+
+/// A builtin's name, the arguments it takes and what it does
+type Native = (&'static str, Arity, NativeFunctionCallback);
 
 const print: NativeFunctionCallback = |args, i| {
 	let mut output = String::default();
@@ -11,26 +28,161 @@ const print: NativeFunctionCallback = |args, i| {
 	i.stdout.push_str(&output);
 	print!("{}", output);
 
-	return Unit;
+	return Ok(Nil);
 };
 
 const println: NativeFunctionCallback = |args, i| {
 	let ret = print(args, i);
-	print(&vec![Str("\n".to_string())], i);
+	print(&vec![Str("\n".to_string())], i)?;
 	return ret;
 };
 
-pub fn get_lib() -> HashMap<String, Object> {
-	[("print", print), ("println", println)]
+/// range(end), range(start, end) or range(start, end, step)
+const range: NativeFunctionCallback = |args, _| {
+	let (start, end) = match args.len() {
+		1 => (0, int_arg(args, 0)?),
+		_ => (int_arg(args, 0)?, int_arg(args, 1)?),
+	};
+	let step = match args.get(2) {
+		Some(_) => int_arg(args, 2)?,
+		None => 1,
+	};
+
+	if step == 0 {
+		return Err("The step of a range can't be 0".into());
+	}
+
+	Ok(Range { start, end, step })
+};
+
+/// error(message) makes an `Error` like the ones runtime errors throw
+const error: NativeFunctionCallback = |args, i| Ok(i.error_object(str_arg(args, 0)?.clone()));
+
+const repr: NativeFunctionCallback = |args, _| Ok(Str(arg(args, 0)?.repr()));
+
+/// The builtins, where the math constants are declared as constants so scripts can't
+/// change them for everything else
+pub fn get_lib() -> Variables {
+	let mut lib: Vec<Native> = vec![
+		("print", Arity::Variadic(0), print),
+		("println", Arity::Variadic(0), println),
+		("repr", Arity::Exact(1), repr),
+		("range", Arity::Range(1, 3), range),
+		("error", Arity::Exact(1), error),
+	];
+	lib.extend(string::functions());
+	lib.extend(iter::functions());
+	lib.extend(result::functions());
+	lib.extend(math::functions());
+	lib.extend(random::functions());
+	lib.extend(input::functions());
+	lib.extend(fs::functions());
+	lib.extend(process::functions());
+	lib.extend(time::functions());
+	lib.extend(map::functions());
+	lib.extend(json::functions());
+
+	let constants = math::constants();
+	Variables {
+		constants: constants.iter().map(|(k, _)| k.to_string()).collect(),
+		values: lib
+			.into_iter()
+			.map(|(k, arity, v)| (k.to_string(), native(k, arity, v)))
+			.chain(constants.into_iter().map(|(k, v)| (k.to_string(), v)))
+			.collect(),
+	}
+}
+
+/// Finds the builtin method `name` of `receiver`, which is the stdlib function of the same
+/// name that gets the receiver as its first argument
+pub(crate) fn method(receiver: &Object, name: &str) -> Option<Object> {
+	let mut methods: Vec<Native> = vec![("repr", Arity::Exact(1), repr)];
+
+	match receiver {
+		Str(_) => {
+			methods.extend(string::str_methods());
+			methods.extend(iter::functions());
+		}
+		List(_) => {
+			methods.extend(string::list_methods());
+			methods.extend(iter::functions());
+		}
+		Int(_) | Float(_) => methods.extend(math::functions()),
+		Range { .. } | Iterator(_) => methods.extend(iter::functions()),
+		Outcome { .. } => methods.extend(result::functions()),
+		Map(_) => methods.extend(map::functions()),
+		_ => {}
+	}
+
+	methods
 		.into_iter()
-		.map(|(k, v)| {
-			(
-				k.to_string(),
-				NativeFunction(Box::from(NativeFunctionDef {
-					name: k.to_string(),
-					callback: v,
-				})),
-			)
-		})
-		.collect()
+		.find(|(k, _, _)| *k == name)
+		.map(|(k, arity, v)| native(k, arity, v))
+}
+
+pub(crate) fn native(name: &str, arity: Arity, callback: NativeFunctionCallback) -> Object {
+	NativeFunction(Rc::new(NativeFunctionDef {
+		name: name.to_string(),
+		arity: arity,
+		callback: callback,
+	}))
+}
+
+/// Returns the argument at `index`, erroring if it wasn't provided
+pub(crate) fn arg(args: &[Object], index: usize) -> Result<&Object, String> {
+	args.get(index)
+		.ok_or_else(|| format!("Missing argument {}", index + 1))
+}
+
+fn type_error<T>(index: usize, expected: &str, got: &Object) -> Result<T, String> {
+	Err(format!(
+		"Expected argument {} to be {expected}, instead got {}",
+		index + 1,
+		got.type_name()
+	))
+}
+
+pub(crate) fn str_arg(args: &[Object], index: usize) -> Result<&String, String> {
+	match arg(args, index)? {
+		Str(v) => Ok(v),
+		v => type_error(index, "a string", v),
+	}
+}
+
+/// Accepts either an int or a float, promoting ints
+pub(crate) fn number_arg(args: &[Object], index: usize) -> Result<f64, String> {
+	match arg(args, index)? {
+		Int(v) => Ok(*v as f64),
+		Float(v) => Ok(*v),
+		v => type_error(index, "a number", v),
+	}
+}
+
+pub(crate) fn int_arg(args: &[Object], index: usize) -> Result<i64, String> {
+	match arg(args, index)? {
+		Int(v) => Ok(*v),
+		v => type_error(index, "an int", v),
+	}
+}
+
+/// An int argument that's used to count or index, so it can't be negative
+pub(crate) fn index_arg(args: &[Object], index: usize) -> Result<usize, String> {
+	match arg(args, index)? {
+		Int(v) if *v >= 0 => Ok(*v as usize),
+		v => type_error(index, "a non-negative int", v),
+	}
+}
+
+pub(crate) fn function_arg(args: &[Object], index: usize) -> Result<Object, String> {
+	match arg(args, index)? {
+		v @ (Function(_) | NativeFunction(_)) => Ok(v.clone()),
+		v => type_error(index, "a function", v),
+	}
+}
+
+pub(crate) fn list_arg(args: &[Object], index: usize) -> Result<Ref<Vec<Object>>, String> {
+	match arg(args, index)? {
+		List(v) => Ok(v.borrow()),
+		v => type_error(index, "a list", v),
+	}
 }
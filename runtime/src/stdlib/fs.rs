@@ -0,0 +1,146 @@
+//! File builtins, which can only touch the directories the interpreter's `FsPolicy` allows.
+//! Relative paths start from the working directory.
+use super::{str_arg, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object, Object::*};
+use std::{
+	env, fs,
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+/// The directories scripts can read and write files in, along with everything inside them.
+/// Nothing is allowed by default, so embedded scripts can't touch files unless the host
+/// lets them. Reading and writing are allowed separately, so leaving out writing makes
+/// the files read-only.
+#[derive(Debug, Clone, Default)]
+pub struct FsPolicy {
+	read: Vec<PathBuf>,
+	write: Vec<PathBuf>,
+}
+
+impl FsPolicy {
+	pub fn allow_read(&mut self, dir: impl AsRef<Path>) -> Result<(), String> {
+		self.read.push(root(dir.as_ref())?);
+		Ok(())
+	}
+
+	pub fn allow_write(&mut self, dir: impl AsRef<Path>) -> Result<(), String> {
+		self.write.push(root(dir.as_ref())?);
+		Ok(())
+	}
+
+	/// Resolves `path`, erroring unless it's inside an allowed directory
+	fn check(&self, path: &str, write: bool) -> Result<PathBuf, String> {
+		let (roots, action) = match write {
+			true => (&self.write, "Writing to"),
+			false => (&self.read, "Reading"),
+		};
+
+		let resolved = resolve(path)?;
+		match roots.iter().any(|v| resolved.starts_with(v)) {
+			true => Ok(resolved),
+			false => Err(format!("{action} \"{path}\" isn't allowed")),
+		}
+	}
+}
+
+fn root(dir: &Path) -> Result<PathBuf, String> {
+	dir.canonicalize()
+		.map_err(|e| format!("Can't allow \"{}\": {e}", dir.display()))
+}
+
+/// Makes `path` absolute with symlinks and `..` resolved, so it can't get out of an allowed
+/// directory. Files that don't exist yet are resolved from the closest parent that does.
+fn resolve(path: &str) -> Result<PathBuf, String> {
+	let full = env::current_dir().map_err(|e| e.to_string())?.join(path);
+
+	let mut missing = vec![];
+	let mut existing = full.as_path();
+	loop {
+		if let Ok(mut resolved) = existing.canonicalize() {
+			resolved.extend(missing.iter().rev());
+			return Ok(resolved);
+		}
+		// a `..` after a missing directory has nothing to resolve against, and a link that
+		// points nowhere could be made to point out of the allowed directories
+		let dangling = existing.symlink_metadata().is_ok();
+		match (existing.file_name(), existing.parent()) {
+			(Some(name), Some(parent)) if !dangling => {
+				missing.push(name);
+				existing = parent;
+			}
+			_ => return Err(format!("Invalid path \"{path}\"")),
+		}
+	}
+}
+
+/// Turns an IO error into a message that says which file it was about
+fn io_error<'a>(action: &'a str, path: &'a str) -> impl Fn(std::io::Error) -> String + 'a {
+	move |e| format!("Can't {action} \"{path}\": {e}")
+}
+
+const read_file: NativeFunctionCallback = |args, i| {
+	let path = str_arg(args, 0)?;
+	let resolved = i.fs.check(path, false)?;
+	Ok(Str(
+		fs::read_to_string(resolved).map_err(io_error("read", path))?
+	))
+};
+
+/// write_file(path, text) replaces the file's contents, making it if it doesn't exist
+const write_file: NativeFunctionCallback = |args, i| {
+	let (path, text) = (str_arg(args, 0)?, str_arg(args, 1)?);
+	let resolved = i.fs.check(path, true)?;
+	fs::write(resolved, text).map_err(io_error("write", path))?;
+	Ok(Nil)
+};
+
+const append_file: NativeFunctionCallback = |args, i| {
+	let (path, text) = (str_arg(args, 0)?, str_arg(args, 1)?);
+	let resolved = i.fs.check(path, true)?;
+	fs::OpenOptions::new()
+		.append(true)
+		.create(true)
+		.open(resolved)
+		.and_then(|mut v| v.write_all(text.as_bytes()))
+		.map_err(io_error("append to", path))?;
+	Ok(Nil)
+};
+
+/// list_dir(path) returns the sorted names of what's in the directory
+const list_dir: NativeFunctionCallback = |args, i| {
+	let path = str_arg(args, 0)?;
+	let resolved = i.fs.check(path, false)?;
+
+	let mut names = vec![];
+	for entry in fs::read_dir(resolved).map_err(io_error("list", path))? {
+		let entry = entry.map_err(io_error("list", path))?;
+		names.push(entry.file_name().to_string_lossy().to_string());
+	}
+	names.sort();
+
+	Ok(Object::list(names.into_iter().map(Str).collect()))
+};
+
+const exists: NativeFunctionCallback = |args, i| {
+	let resolved = i.fs.check(str_arg(args, 0)?, false)?;
+	Ok(Bool(resolved.exists()))
+};
+
+const remove_file: NativeFunctionCallback = |args, i| {
+	let path = str_arg(args, 0)?;
+	let resolved = i.fs.check(path, true)?;
+	fs::remove_file(resolved).map_err(io_error("remove", path))?;
+	Ok(Nil)
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("read_file", Arity::Exact(1), read_file),
+		("write_file", Arity::Exact(2), write_file),
+		("append_file", Arity::Exact(2), append_file),
+		("list_dir", Arity::Exact(1), list_dir),
+		("exists", Arity::Exact(1), exists),
+		("remove_file", Arity::Exact(1), remove_file),
+	]
+}
@@ -0,0 +1,86 @@
+//! Reading input from the source set on the interpreter, which hosts can swap out to feed
+//! scripts fixed input or to stop them from reading.
+use super::{print, str_arg, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object::*};
+use std::io::{stdin, stdout, BufRead, Read, Write};
+
+/// Where `input`, `read_line` and `read_all` read from
+#[derive(Debug, Clone, Default)]
+pub enum Input {
+	#[default]
+	Stdin,
+	/// Text that's read instead of stdin, which is used up as it's read
+	Buffer(String),
+	/// Reading throws an error
+	Disabled,
+}
+
+impl Input {
+	/// Returns the next line without its line ending, or `None` at the end of the input
+	pub fn read_line(&mut self) -> Result<Option<String>, String> {
+		let mut line = String::new();
+		match self {
+			Input::Stdin => {
+				stdin()
+					.lock()
+					.read_line(&mut line)
+					.map_err(|e| e.to_string())?;
+			}
+			Input::Buffer(text) => {
+				let end = text.find('\n').map_or(text.len(), |i| i + 1);
+				line = text.drain(..end).collect();
+			}
+			Input::Disabled => return Err("Reading input is disabled".to_string()),
+		}
+
+		if line.is_empty() {
+			return Ok(None);
+		}
+		let trimmed = line.strip_suffix('\n').unwrap_or(&line);
+		Ok(Some(
+			trimmed.strip_suffix('\r').unwrap_or(trimmed).to_string(),
+		))
+	}
+
+	/// Returns everything left to read
+	pub fn read_all(&mut self) -> Result<String, String> {
+		match self {
+			Input::Stdin => {
+				let mut text = String::new();
+				stdin()
+					.lock()
+					.read_to_string(&mut text)
+					.map_err(|e| e.to_string())?;
+				Ok(text)
+			}
+			Input::Buffer(text) => Ok(std::mem::take(text)),
+			Input::Disabled => Err("Reading input is disabled".to_string()),
+		}
+	}
+}
+
+/// read_line() returns the next line, or nil when there's nothing left
+const read_line: NativeFunctionCallback = |_, i| match i.input.read_line()? {
+	Some(line) => Ok(Str(line)),
+	None => Ok(Nil),
+};
+
+/// input(prompt) prints the prompt, then reads a line like `read_line`
+const input: NativeFunctionCallback = |args, i| {
+	if !args.is_empty() {
+		print(&vec![Str(str_arg(args, 0)?.clone())], i)?;
+		// the prompt has no line ending, so it wouldn't be shown before reading otherwise
+		stdout().flush().map_err(|e| e.to_string())?;
+	}
+	read_line(&vec![], i)
+};
+
+const read_all: NativeFunctionCallback = |_, i| Ok(Str(i.input.read_all()?));
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("input", Arity::Range(0, 1), input),
+		("read_line", Arity::Exact(0), read_line),
+		("read_all", Arity::Exact(0), read_all),
+	]
+}
@@ -0,0 +1,94 @@
+//! Functions over iterables. Apart from `reduce` and `list` they're lazy, returning an
+//! iterator that does its work as it's looped over.
+use super::{arg, function_arg, index_arg, Native};
+use crate::{
+	iterator::Iter,
+	object::{Arity, NativeFunctionCallback, Object},
+};
+
+fn iter_arg(args: &[Object], index: usize) -> Result<Iter, String> {
+	Iter::new(arg(args, index)?)
+}
+
+/// map(iterable, f)
+const map: NativeFunctionCallback = |args, _| {
+	let f = function_arg(args, 1)?;
+	Ok(Object::iterator(Iter::Map(Box::new(iter_arg(args, 0)?), f)))
+};
+
+/// filter(iterable, f) keeps the items that f returns true for
+const filter: NativeFunctionCallback = |args, _| {
+	let f = function_arg(args, 1)?;
+	Ok(Object::iterator(Iter::Filter(
+		Box::new(iter_arg(args, 0)?),
+		f,
+	)))
+};
+
+/// reduce(iterable, f, initial) where the first item is used when there's no initial value
+const reduce: NativeFunctionCallback = |args, i| {
+	let mut items = iter_arg(args, 0)?;
+	let f = function_arg(args, 1)?;
+
+	let mut acc = match args.get(2) {
+		Some(v) => v.clone(),
+		None => match items.next(i)? {
+			Some(v) => v,
+			None => return Err("Can't reduce an empty iterable without an initial value".into()),
+		},
+	};
+
+	while let Some(v) = items.next(i)? {
+		acc = i.call(&f, &vec![acc, v])?;
+	}
+
+	Ok(acc)
+};
+
+/// zip(a, b) pairs up items as `[a, b]` lists, stopping at the end of the shorter one
+const zip: NativeFunctionCallback = |args, _| {
+	let left = iter_arg(args, 0)?;
+	let right = iter_arg(args, 1)?;
+	Ok(Object::iterator(Iter::Zip(Box::new(left), Box::new(right))))
+};
+
+/// enumerate(iterable) gives `[index, item]` lists
+const enumerate: NativeFunctionCallback = |args, _| {
+	Ok(Object::iterator(Iter::Enumerate(
+		Box::new(iter_arg(args, 0)?),
+		0,
+	)))
+};
+
+/// take(iterable, n) stops after the first n items
+const take: NativeFunctionCallback = |args, _| {
+	let count = index_arg(args, 1)?;
+	Ok(Object::iterator(Iter::Take(
+		Box::new(iter_arg(args, 0)?),
+		count,
+	)))
+};
+
+/// list(iterable) collects every item into a new list
+const list: NativeFunctionCallback = |args, i| {
+	let mut items = iter_arg(args, 0)?;
+	let mut res = vec![];
+
+	while let Some(v) = items.next(i)? {
+		res.push(v);
+	}
+
+	Ok(Object::list(res))
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("map", Arity::Exact(2), map),
+		("filter", Arity::Exact(2), filter),
+		("reduce", Arity::Range(2, 3), reduce),
+		("zip", Arity::Exact(2), zip),
+		("enumerate", Arity::Exact(1), enumerate),
+		("take", Arity::Exact(2), take),
+		("list", Arity::Exact(1), list),
+	]
+}
@@ -0,0 +1,432 @@
+//! JSON, where objects are maps, arrays are lists and null is nil. Numbers without a
+//! fraction or exponent are ints when they fit in one.
+use super::{arg, index_arg, str_arg, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object, Object::*};
+use std::{iter::Peekable, str::Chars};
+
+/// How deeply arrays and objects can be nested, so parsing can't overflow the stack
+const MAX_DEPTH: usize = 512;
+/// The most spaces `json_stringify` indents by, like `JSON.stringify` in javascript
+const MAX_INDENT: usize = 10;
+
+struct JsonParser<'a> {
+	chars: Peekable<Chars<'a>>,
+	ln: usize,
+	col: usize,
+	depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+	fn new(text: &'a str) -> Self {
+		JsonParser {
+			chars: text.chars().peekable(),
+			ln: 1,
+			col: 1,
+			depth: 0,
+		}
+	}
+
+	fn parse(&mut self) -> Result<Object, String> {
+		let value = self.value()?;
+		self.skip_whitespace();
+		match self.chars.peek().copied() {
+			Some(c) => Err(self.error(format!("Unexpected {c:?} after the value"))),
+			None => Ok(value),
+		}
+	}
+
+	/// Errors are positioned at the character that couldn't be parsed
+	fn error(&self, text: String) -> String {
+		format!(
+			"Invalid JSON at line {}, column {}: {text}",
+			self.ln, self.col
+		)
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.chars.next()?;
+		if c == '\n' {
+			self.ln += 1;
+			self.col = 1;
+		} else {
+			self.col += 1;
+		}
+		Some(c)
+	}
+
+	/// Moves past the next character if `f` accepts it
+	fn next_if(&mut self, f: impl Fn(char) -> bool) -> Option<char> {
+		match self.chars.peek() {
+			Some(c) if f(*c) => self.next(),
+			_ => None,
+		}
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), String> {
+		match self.chars.peek().copied() {
+			Some(c) if c == expected => {
+				self.next();
+				Ok(())
+			}
+			Some(c) => Err(self.error(format!("Expected {expected:?}, instead got {c:?}"))),
+			None => Err(self.error(format!("Expected {expected:?}, instead got the end"))),
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+			self.next();
+		}
+	}
+
+	fn value(&mut self) -> Result<Object, String> {
+		self.skip_whitespace();
+		match self.chars.peek().copied() {
+			Some('{') => self.nested(Self::object),
+			Some('[') => self.nested(Self::array),
+			Some('"') => Ok(Str(self.string()?)),
+			Some('-' | '0'..='9') => self.number(),
+			Some('t') => self.keyword("true", Bool(true)),
+			Some('f') => self.keyword("false", Bool(false)),
+			Some('n') => self.keyword("null", Nil),
+			Some(c) => Err(self.error(format!("Expected a value, instead got {c:?}"))),
+			None => Err(self.error("Expected a value, instead got the end".to_string())),
+		}
+	}
+
+	fn nested(&mut self, f: fn(&mut Self) -> Result<Object, String>) -> Result<Object, String> {
+		if self.depth == MAX_DEPTH {
+			return Err(self.error(format!("Nested more than {MAX_DEPTH} levels deep")));
+		}
+		self.depth += 1;
+		let res = f(self);
+		self.depth -= 1;
+		res
+	}
+
+	fn keyword(&mut self, word: &str, value: Object) -> Result<Object, String> {
+		for expected in word.chars() {
+			if self.chars.peek() != Some(&expected) {
+				return Err(self.error(format!("Expected {word}")));
+			}
+			self.next();
+		}
+		Ok(value)
+	}
+
+	fn object(&mut self) -> Result<Object, String> {
+		self.expect('{')?;
+		let mut entries: Vec<(String, Object)> = vec![];
+
+		self.skip_whitespace();
+		if self.chars.peek() == Some(&'}') {
+			self.next();
+			return Ok(Object::map(entries));
+		}
+
+		loop {
+			self.skip_whitespace();
+			if self.chars.peek() != Some(&'"') {
+				return Err(self.error("Expected a string key".to_string()));
+			}
+			let key = self.string()?;
+			self.skip_whitespace();
+			self.expect(':')?;
+			let value = self.value()?;
+
+			// a repeated key replaces the earlier value when the map is made
+			entries.push((key, value));
+
+			self.skip_whitespace();
+			match self.next_if(|v| v == ',' || v == '}') {
+				Some(',') => continue,
+				Some(_) => return Ok(Object::map(entries)),
+				None => return Err(self.error("Expected ',' or '}'".to_string())),
+			}
+		}
+	}
+
+	fn array(&mut self) -> Result<Object, String> {
+		self.expect('[')?;
+		let mut items = vec![];
+
+		self.skip_whitespace();
+		if self.chars.peek() == Some(&']') {
+			self.next();
+			return Ok(Object::list(items));
+		}
+
+		loop {
+			items.push(self.value()?);
+			self.skip_whitespace();
+			match self.next_if(|v| v == ',' || v == ']') {
+				Some(',') => continue,
+				Some(_) => return Ok(Object::list(items)),
+				None => return Err(self.error("Expected ',' or ']'".to_string())),
+			}
+		}
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		self.expect('"')?;
+		let mut res = String::new();
+
+		loop {
+			let c = match self.next() {
+				Some(c) => c,
+				None => return Err(self.error("Unterminated string".to_string())),
+			};
+			match c {
+				'"' => return Ok(res),
+				'\\' => {
+					let escaped = match self.next() {
+						Some('"') => '"',
+						Some('\\') => '\\',
+						Some('/') => '/',
+						Some('b') => '\u{8}',
+						Some('f') => '\u{c}',
+						Some('n') => '\n',
+						Some('r') => '\r',
+						Some('t') => '\t',
+						Some('u') => self.unicode_escape()?,
+						_ => return Err(self.error("Invalid escape".to_string())),
+					};
+					res.push(escaped);
+				}
+				c if (c as u32) < 0x20 => {
+					return Err(self.error("Control characters must be escaped".to_string()))
+				}
+				c => res.push(c),
+			}
+		}
+	}
+
+	/// The character of a `\uXXXX` escape, which takes two escapes for a surrogate pair
+	fn unicode_escape(&mut self) -> Result<char, String> {
+		let high = self.hex_digits()?;
+		if !(0xD800..0xDC00).contains(&high) {
+			return char::from_u32(high).ok_or_else(|| self.error("Invalid escape".to_string()));
+		}
+
+		if self.next() != Some('\\') || self.next() != Some('u') {
+			return Err(self.error("Expected the second half of a surrogate pair".to_string()));
+		}
+		let low = self.hex_digits()?;
+		if !(0xDC00..0xE000).contains(&low) {
+			return Err(self.error("Invalid surrogate pair".to_string()));
+		}
+		let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+		char::from_u32(code).ok_or_else(|| self.error("Invalid escape".to_string()))
+	}
+
+	fn hex_digits(&mut self) -> Result<u32, String> {
+		let mut code = 0;
+		for _ in 0..4 {
+			match self.next().and_then(|v| v.to_digit(16)) {
+				Some(v) => code = code * 16 + v,
+				None => return Err(self.error("Expected 4 hex digits".to_string())),
+			}
+		}
+		Ok(code)
+	}
+
+	fn number(&mut self) -> Result<Object, String> {
+		let mut text = String::new();
+		let mut is_float = false;
+
+		if self.chars.peek() == Some(&'-') {
+			text.push('-');
+			self.next();
+		}
+
+		// no leading zeros, unless the whole part is just 0
+		match self.chars.peek() {
+			Some('0') => text.push(self.next().unwrap()),
+			Some('1'..='9') => self.digits(&mut text),
+			_ => return Err(self.error("Expected a digit".to_string())),
+		}
+
+		if self.chars.peek() == Some(&'.') {
+			is_float = true;
+			text.push(self.next().unwrap());
+			if !matches!(self.chars.peek(), Some('0'..='9')) {
+				return Err(self.error("Expected a digit".to_string()));
+			}
+			self.digits(&mut text);
+		}
+
+		if matches!(self.chars.peek(), Some('e' | 'E')) {
+			is_float = true;
+			text.push(self.next().unwrap());
+			if matches!(self.chars.peek(), Some('+' | '-')) {
+				text.push(self.next().unwrap());
+			}
+			if !matches!(self.chars.peek(), Some('0'..='9')) {
+				return Err(self.error("Expected a digit".to_string()));
+			}
+			self.digits(&mut text);
+		}
+
+		if !is_float {
+			if let Ok(v) = text.parse::<i64>() {
+				return Ok(Int(v));
+			}
+		}
+		// the format was checked above, so only ints too big for an i64 get here unparsed
+		Ok(Float(text.parse::<f64>().unwrap()))
+	}
+
+	fn digits(&mut self, text: &mut String) {
+		while let Some(c @ '0'..='9') = self.chars.peek().copied() {
+			text.push(c);
+			self.next();
+		}
+	}
+}
+
+struct Serializer {
+	/// Spaces to indent nested values by, or none to put everything on one line
+	indent: usize,
+	/// Lists and maps being written, so ones that contain themselves can be caught
+	containers: Vec<*const ()>,
+}
+
+impl Serializer {
+	fn write(&mut self, value: &Object, depth: usize, out: &mut String) -> Result<(), String> {
+		match value {
+			Nil => out.push_str("null"),
+			Bool(v) => out.push_str(&v.to_string()),
+			Int(v) => out.push_str(&v.to_string()),
+			Float(v) if v.is_finite() => out.push_str(&value.to_string()),
+			Float(v) => return Err(format!("Can't convert {v} to JSON")),
+			Str(v) => write_string(v, out),
+			List(v) => {
+				self.enter(v.as_ptr() as *const ())?;
+				let items = v.borrow();
+				let items: Vec<(Option<&String>, &Object)> =
+					items.iter().map(|v| (None, v)).collect();
+				self.write_container(&items, ('[', ']'), depth, out)?;
+				self.containers.pop();
+			}
+			Map(v) => {
+				self.enter(v.as_ptr() as *const ())?;
+				let entries = v.borrow();
+				let entries: Vec<(Option<&String>, &Object)> =
+					entries.iter().map(|(k, v)| (Some(k), v)).collect();
+				self.write_container(&entries, ('{', '}'), depth, out)?;
+				self.containers.pop();
+			}
+			// instances are written as objects of their fields
+			Instance(v) => {
+				self.enter(v.as_ptr() as *const ())?;
+				let instance = v.borrow();
+				let fields: Vec<(Option<&String>, &Object)> = instance
+					.def
+					.fields
+					.iter()
+					.zip(instance.fields.iter())
+					.map(|(k, v)| (Some(k), v))
+					.collect();
+				self.write_container(&fields, ('{', '}'), depth, out)?;
+				self.containers.pop();
+			}
+			v => return Err(format!("Can't convert {} to JSON", v.type_name())),
+		}
+		Ok(())
+	}
+
+	fn enter(&mut self, container: *const ()) -> Result<(), String> {
+		if self.containers.contains(&container) {
+			return Err("Can't convert a value that contains itself to JSON".to_string());
+		}
+		self.containers.push(container);
+		Ok(())
+	}
+
+	/// Writes an array, or an object when the items have keys
+	fn write_container(
+		&mut self,
+		items: &[(Option<&String>, &Object)],
+		(open, close): (char, char),
+		depth: usize,
+		out: &mut String,
+	) -> Result<(), String> {
+		out.push(open);
+		for (i, (key, value)) in items.iter().enumerate() {
+			if i > 0 {
+				out.push(',');
+			}
+			self.newline(depth + 1, out);
+			if let Some(key) = key {
+				write_string(key, out);
+				out.push(':');
+				if self.indent > 0 {
+					out.push(' ');
+				}
+			}
+			self.write(value, depth + 1, out)?;
+		}
+		if !items.is_empty() {
+			self.newline(depth, out);
+		}
+		out.push(close);
+		Ok(())
+	}
+
+	fn newline(&self, depth: usize, out: &mut String) {
+		if self.indent > 0 {
+			out.push('\n');
+			out.push_str(&" ".repeat(self.indent * depth));
+		}
+	}
+}
+
+fn write_string(text: &str, out: &mut String) {
+	out.push('"');
+	for c in text.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+}
+
+/// json_parse(text)
+const json_parse: NativeFunctionCallback =
+	|args, _| Ok(JsonParser::new(str_arg(args, 0)?).parse()?);
+
+/// json_stringify(value, indent) where the indent is a number of spaces. Without it, the
+/// JSON is written on one line
+const json_stringify: NativeFunctionCallback = |args, _| {
+	let indent = match args.get(1) {
+		Some(_) => index_arg(args, 1)?,
+		None => 0,
+	};
+	if indent > MAX_INDENT {
+		return Err(
+			format!("Can't indent by more than {MAX_INDENT} spaces, instead got {indent}").into(),
+		);
+	}
+
+	let mut serializer = Serializer {
+		indent: indent,
+		containers: vec![],
+	};
+
+	let mut out = String::new();
+	serializer.write(arg(args, 0)?, 0, &mut out)?;
+	Ok(Str(out))
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("json_parse", Arity::Exact(1), json_parse),
+		("json_stringify", Arity::Range(1, 2), json_stringify),
+	]
+}
@@ -0,0 +1,48 @@
+//! Functions for maps, which are made by `new_map()` or `json_parse` and filled in by
+//! assigning to keys like `m["key"] = value`.
+use super::{arg, str_arg, type_error, Native};
+use crate::object::{Arity, MapEntries, NativeFunctionCallback, Object, Object::*};
+use std::{cell::RefCell, rc::Rc};
+
+fn map_arg(args: &[Object], index: usize) -> Result<&Rc<RefCell<MapEntries>>, String> {
+	match arg(args, index)? {
+		Map(v) => Ok(v),
+		v => type_error(index, "a map", v),
+	}
+}
+
+const new_map: NativeFunctionCallback = |_, _| Ok(Object::map(vec![]));
+
+/// keys(map) in the order they were added
+const keys: NativeFunctionCallback = |args, _| {
+	let map = map_arg(args, 0)?.borrow();
+	Ok(Object::list(
+		map.iter().map(|(k, _)| Str(k.clone())).collect(),
+	))
+};
+
+const values: NativeFunctionCallback = |args, _| {
+	let map = map_arg(args, 0)?.borrow();
+	Ok(Object::list(map.iter().map(|(_, v)| v.clone()).collect()))
+};
+
+const has_key: NativeFunctionCallback = |args, _| {
+	let key = str_arg(args, 1)?;
+	Ok(Bool(map_arg(args, 0)?.borrow().contains_key(key)))
+};
+
+/// remove_key(map, key) returns the value that was removed, or nil if the key wasn't there
+const remove_key: NativeFunctionCallback = |args, _| {
+	let key = str_arg(args, 1)?;
+	Ok(map_arg(args, 0)?.borrow_mut().remove(key).unwrap_or(Nil))
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("new_map", Arity::Exact(0), new_map),
+		("keys", Arity::Exact(1), keys),
+		("values", Arity::Exact(1), values),
+		("has_key", Arity::Exact(2), has_key),
+		("remove_key", Arity::Exact(2), remove_key),
+	]
+}
@@ -0,0 +1,174 @@
+//! Number functions. They take ints or floats, and keep ints as ints where the result is
+//! always whole.
+use super::{arg, index_arg, number_arg, str_arg, type_error, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object, Object::*};
+use std::cmp::Ordering;
+
+/// Returns a number argument as it is, so ints stay ints
+fn num_arg(args: &[Object], index: usize) -> Result<&Object, String> {
+	match arg(args, index)? {
+		v @ (Int(_) | Float(_)) => Ok(v),
+		v => type_error(index, "a number", v),
+	}
+}
+
+/// Rounds with `f`, giving an int
+fn to_int(args: &[Object], f: fn(f64) -> f64) -> Result<Object, String> {
+	match num_arg(args, 0)? {
+		Int(v) => Ok(Int(*v)),
+		_ => {
+			let v = f(number_arg(args, 0)?);
+			// the float range is wider, and NaN and infinity have no int
+			if !v.is_finite() || v < i64::MIN as f64 || v >= i64::MAX as f64 {
+				return Err(format!("Can't convert {v} to an int"));
+			}
+			Ok(Int(v as i64))
+		}
+	}
+}
+
+fn float_fn(args: &[Object], f: fn(f64) -> f64) -> Result<Object, String> {
+	Ok(Float(f(number_arg(args, 0)?)))
+}
+
+/// The argument that `wanted` orders first, where NaN is never chosen over a number
+fn pick(args: &[Object], wanted: Ordering) -> Result<Object, String> {
+	let mut res = num_arg(args, 0)?;
+	for i in 1..args.len() {
+		let v = num_arg(args, i)?;
+		if v.partial_cmp(res) == Some(wanted) || matches!(res, Float(r) if r.is_nan()) {
+			res = v;
+		}
+	}
+	Ok(res.clone())
+}
+
+const abs: NativeFunctionCallback = |args, _| match num_arg(args, 0)? {
+	Int(v) => match v.checked_abs() {
+		Some(v) => Ok(Int(v)),
+		None => Err(format!("Integer overflow in abs({v})").into()),
+	},
+	_ => float_fn(args, f64::abs).map_err(Into::into),
+};
+
+const floor: NativeFunctionCallback = |args, _| Ok(to_int(args, f64::floor)?);
+
+const ceil: NativeFunctionCallback = |args, _| Ok(to_int(args, f64::ceil)?);
+
+/// round(x) rounds halfway values away from zero
+const round: NativeFunctionCallback = |args, _| Ok(to_int(args, f64::round)?);
+
+const sqrt: NativeFunctionCallback = |args, _| Ok(float_fn(args, f64::sqrt)?);
+
+/// pow(base, exponent), which is an int when both are and the exponent isn't negative
+const pow: NativeFunctionCallback = |args, _| match (num_arg(args, 0)?, num_arg(args, 1)?) {
+	(Int(l), Int(r)) if *r >= 0 => match u32::try_from(*r).ok().and_then(|r| l.checked_pow(r)) {
+		Some(v) => Ok(Int(v)),
+		None => Err(format!("Integer overflow in pow({l}, {r})").into()),
+	},
+	_ => Ok(Float(number_arg(args, 0)?.powf(number_arg(args, 1)?))),
+};
+
+/// min(a, b, ...) returns the smallest number as it was given
+const min: NativeFunctionCallback = |args, _| Ok(pick(args, Ordering::Less)?);
+
+const max: NativeFunctionCallback = |args, _| Ok(pick(args, Ordering::Greater)?);
+
+/// clamp(x, low, high) limits x to be between low and high
+const clamp: NativeFunctionCallback = |args, _| {
+	let (v, low, high) = (num_arg(args, 0)?, num_arg(args, 1)?, num_arg(args, 2)?);
+	if high < low {
+		return Err(format!("The low bound {low} is greater than the high bound {high}").into());
+	}
+
+	let res = match v {
+		v if v < low => low,
+		v if v > high => high,
+		v => v,
+	};
+	Ok(res.clone())
+};
+
+const sin: NativeFunctionCallback = |args, _| Ok(float_fn(args, f64::sin)?);
+
+const cos: NativeFunctionCallback = |args, _| Ok(float_fn(args, f64::cos)?);
+
+const tan: NativeFunctionCallback = |args, _| Ok(float_fn(args, f64::tan)?);
+
+/// atan2(y, x) is the angle from the x axis to the point (x, y)
+const atan2: NativeFunctionCallback =
+	|args, _| Ok(Float(number_arg(args, 0)?.atan2(number_arg(args, 1)?)));
+
+/// log(x, base) where the base defaults to e
+const log: NativeFunctionCallback = |args, _| {
+	let v = number_arg(args, 0)?;
+	match args.get(1) {
+		Some(_) => Ok(Float(v.log(number_arg(args, 1)?))),
+		None => Ok(Float(v.ln())),
+	}
+};
+
+const exp: NativeFunctionCallback = |args, _| Ok(float_fn(args, f64::exp)?);
+
+const is_nan: NativeFunctionCallback = |args, _| Ok(Bool(number_arg(args, 0)?.is_nan()));
+
+const is_finite: NativeFunctionCallback = |args, _| Ok(Bool(number_arg(args, 0)?.is_finite()));
+
+/// parse_number(s) gives an int if s is one, otherwise a float
+const parse_number: NativeFunctionCallback = |args, _| {
+	let s = str_arg(args, 0)?.trim();
+	if let Ok(v) = s.parse::<i64>() {
+		return Ok(Int(v));
+	}
+	match s.parse::<f64>() {
+		Ok(v) => Ok(Float(v)),
+		Err(_) => Err(format!("Can't parse {} as a number", Str(s.to_string()).repr()).into()),
+	}
+};
+
+/// The most digits `to_fixed` writes after the decimal point
+const MAX_DIGITS: usize = 100;
+
+/// to_fixed(x, digits) formats x with that many digits after the decimal point
+const to_fixed: NativeFunctionCallback = |args, _| {
+	let v = number_arg(args, 0)?;
+	let digits = index_arg(args, 1)?;
+	if digits > MAX_DIGITS {
+		return Err(
+			format!("Can't show more than {MAX_DIGITS} digits, instead got {digits}").into(),
+		);
+	}
+	Ok(Str(format!("{v:.digits$}")))
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("abs", Arity::Exact(1), abs),
+		("floor", Arity::Exact(1), floor),
+		("ceil", Arity::Exact(1), ceil),
+		("round", Arity::Exact(1), round),
+		("sqrt", Arity::Exact(1), sqrt),
+		("pow", Arity::Exact(2), pow),
+		("min", Arity::Variadic(1), min),
+		("max", Arity::Variadic(1), max),
+		("clamp", Arity::Exact(3), clamp),
+		("sin", Arity::Exact(1), sin),
+		("cos", Arity::Exact(1), cos),
+		("tan", Arity::Exact(1), tan),
+		("atan2", Arity::Exact(2), atan2),
+		("log", Arity::Range(1, 2), log),
+		("exp", Arity::Exact(1), exp),
+		("is_nan", Arity::Exact(1), is_nan),
+		("is_finite", Arity::Exact(1), is_finite),
+		("parse_number", Arity::Exact(1), parse_number),
+		("to_fixed", Arity::Exact(2), to_fixed),
+	]
+}
+
+/// Constants that are globals rather than functions
+pub(super) fn constants() -> Vec<(&'static str, Object)> {
+	vec![
+		("pi", Float(std::f64::consts::PI)),
+		("e", Float(std::f64::consts::E)),
+	]
+}
@@ -0,0 +1,43 @@
+//! What a script gets from the process running it: its arguments, environment variables if
+//! they're allowed, and a way to stop with an exit code.
+use super::{int_arg, str_arg, Native};
+use crate::{
+	error::Error,
+	object::{Arity, NativeFunctionCallback, Object, Object::*},
+};
+
+/// args() returns the script's arguments. Named differently so `args` can still be used below
+const script_args: NativeFunctionCallback =
+	|_, i| Ok(Object::list(i.args.iter().cloned().map(Str).collect()));
+
+/// env(name) returns the variable, or nil if it isn't set
+const env: NativeFunctionCallback = |args, i| {
+	let name = str_arg(args, 0)?;
+	if !i.allow_env {
+		return Err(format!("Reading the environment variable {name} isn't allowed").into());
+	}
+	match std::env::var(name) {
+		Ok(v) => Ok(Str(v)),
+		Err(_) => Ok(Nil),
+	}
+};
+
+/// exit(code) stops the program, where the code defaults to 0
+const exit: NativeFunctionCallback = |args, _| {
+	let code = match args.is_empty() {
+		true => 0,
+		false => int_arg(args, 0)?,
+	};
+	match i32::try_from(code) {
+		Ok(code) => Err(Error::Exit(code)),
+		Err(_) => Err(format!("Exit code {code} is out of range").into()),
+	}
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("args", Arity::Exact(0), script_args),
+		("env", Arity::Exact(1), env),
+		("exit", Arity::Range(0, 1), exit),
+	]
+}
@@ -0,0 +1,106 @@
+//! Random numbers from a generator owned by the interpreter, so a run can be replayed by
+//! giving it the same seed with `seed(n)` or the `--seed` flag.
+use super::{arg, int_arg, list_arg, type_error, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object::*};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SplitMix64, which is small and gives good numbers from any seed, including 0
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+	state: u64,
+}
+
+impl Rng {
+	pub fn new(state: u64) -> Rng {
+		Rng { state: state }
+	}
+
+	/// Seeded from the clock, for runs that don't ask for a seed
+	pub fn from_time() -> Rng {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default();
+		Rng::new(now.as_nanos() as u64)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+		z ^ (z >> 31)
+	}
+
+	/// A float from 0 up to, but not including, 1
+	pub fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	/// An int from 0 up to, but not including, `bound`, without favouring any of them
+	pub fn below(&mut self, bound: u64) -> u64 {
+		// values past the last whole multiple of bound would make the low results likelier
+		let limit = u64::MAX - u64::MAX % bound;
+		loop {
+			let v = self.next_u64();
+			if v < limit {
+				return v % bound;
+			}
+		}
+	}
+}
+
+const random: NativeFunctionCallback = |_, i| Ok(Float(i.rng.next_f64()));
+
+/// random_int(low, high) where both ends can be returned
+const random_int: NativeFunctionCallback = |args, i| {
+	let (low, high) = (int_arg(args, 0)?, int_arg(args, 1)?);
+	if low > high {
+		return Err(format!("The low bound {low} is greater than the high bound {high}").into());
+	}
+
+	// the span is one more than the difference, which only overflows for the whole i64 range
+	let span = high.abs_diff(low).wrapping_add(1);
+	let offset = match span {
+		0 => i.rng.next_u64(),
+		_ => i.rng.below(span),
+	};
+	Ok(Int(low.wrapping_add(offset as i64)))
+};
+
+/// shuffle(list) reorders the list in place
+const shuffle: NativeFunctionCallback = |args, i| match arg(args, 0)? {
+	List(list) => {
+		let mut list = list.borrow_mut();
+		for j in (1..list.len()).rev() {
+			let k = i.rng.below(j as u64 + 1) as usize;
+			list.swap(j, k);
+		}
+		Ok(Nil)
+	}
+	v => Ok(type_error(0, "a list", v)?),
+};
+
+/// choice(list) returns a random item
+const choice: NativeFunctionCallback = |args, i| {
+	let list = list_arg(args, 0)?;
+	if list.is_empty() {
+		return Err("Can't choose from an empty list".into());
+	}
+	Ok(list[i.rng.below(list.len() as u64) as usize].clone())
+};
+
+/// seed(n) restarts the generator, so the same numbers come out after the same seed
+const seed: NativeFunctionCallback = |args, i| {
+	i.seed(int_arg(args, 0)? as u64);
+	Ok(Nil)
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("random", Arity::Exact(0), random),
+		("random_int", Arity::Exact(2), random_int),
+		("shuffle", Arity::Exact(1), shuffle),
+		("choice", Arity::Exact(1), choice),
+		("seed", Arity::Exact(1), seed),
+	]
+}
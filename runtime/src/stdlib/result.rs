@@ -0,0 +1,50 @@
+//! Results are made by `ok` and `err`, and unwrapped by these functions or by `?`
+use super::{arg, type_error, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object, Object::*};
+
+fn result_arg(args: &[Object], index: usize) -> Result<(bool, &Object), String> {
+	match arg(args, index)? {
+		Outcome { ok: success, value } => Ok((*success, value)),
+		v => type_error(index, "a result", v),
+	}
+}
+
+/// ok(value)
+const ok: NativeFunctionCallback = |args, _| {
+	Ok(Outcome {
+		ok: true,
+		value: Box::new(arg(args, 0)?.clone()),
+	})
+};
+
+/// err(value), where the value is usually a message or an `Error`
+const err: NativeFunctionCallback = |args, _| {
+	Ok(Outcome {
+		ok: false,
+		value: Box::new(arg(args, 0)?.clone()),
+	})
+};
+
+const is_ok: NativeFunctionCallback = |args, _| Ok(Bool(result_arg(args, 0)?.0));
+
+/// unwrap(result) gets the value of an ok result and throws on an err result
+const unwrap: NativeFunctionCallback = |args, _| match result_arg(args, 0)? {
+	(true, value) => Ok(value.clone()),
+	(false, value) => Err(format!("Called unwrap on err({})", value.repr()).into()),
+};
+
+/// unwrap_or(result, default)
+const unwrap_or: NativeFunctionCallback = |args, _| match result_arg(args, 0)? {
+	(true, value) => Ok(value.clone()),
+	(false, _) => Ok(arg(args, 1)?.clone()),
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("ok", Arity::Exact(1), ok),
+		("err", Arity::Exact(1), err),
+		("is_ok", Arity::Exact(1), is_ok),
+		("unwrap", Arity::Exact(1), unwrap),
+		("unwrap_or", Arity::Exact(2), unwrap_or),
+	]
+}
@@ -0,0 +1,139 @@
+//! String functions. Positions and lengths count characters, not bytes.
+use super::{arg, index_arg, list_arg, str_arg, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object, Object::*};
+
+const len: NativeFunctionCallback = |args, _| match arg(args, 0)? {
+	Str(v) => Ok(Int(v.chars().count() as i64)),
+	List(v) => Ok(Int(v.borrow().len() as i64)),
+	Map(v) => Ok(Int(v.borrow().len() as i64)),
+	v => Err(format!("`len` doesn't support {}", v.type_name()).into()),
+};
+
+/// substr(s, start, length) where length defaults to the rest of the string
+const substr: NativeFunctionCallback = |args, _| {
+	let s = str_arg(args, 0)?;
+	let start = index_arg(args, 1)?;
+	let count = s.chars().count();
+
+	if start > count {
+		return Err(format!("Start {start} is out of range for length {count}").into());
+	}
+
+	let length = match args.get(2) {
+		Some(_) => index_arg(args, 2)?,
+		None => count - start,
+	};
+
+	Ok(Str(s.chars().skip(start).take(length).collect()))
+};
+
+/// split(s, separator) where leaving out the separator splits on whitespace
+const split: NativeFunctionCallback = |args, _| {
+	let s = str_arg(args, 0)?;
+
+	let parts: Vec<Object> = match args.get(1) {
+		Some(_) => {
+			let sep = str_arg(args, 1)?;
+			if sep.is_empty() {
+				return Err("Separator can't be empty, use `chars` instead".into());
+			}
+			s.split(sep.as_str()).map(|v| Str(v.to_string())).collect()
+		}
+		None => s.split_whitespace().map(|v| Str(v.to_string())).collect(),
+	};
+
+	Ok(Object::list(parts))
+};
+
+/// join(list, separator) where the separator defaults to nothing
+const join: NativeFunctionCallback = |args, _| {
+	let list = list_arg(args, 0)?;
+	let sep = match args.get(1) {
+		Some(_) => str_arg(args, 1)?.as_str(),
+		None => "",
+	};
+
+	let items: Vec<String> = list.iter().map(|v| v.to_string()).collect();
+	Ok(Str(items.join(sep)))
+};
+
+const trim: NativeFunctionCallback = |args, _| Ok(Str(str_arg(args, 0)?.trim().to_string()));
+
+const upper: NativeFunctionCallback = |args, _| Ok(Str(str_arg(args, 0)?.to_uppercase()));
+
+const lower: NativeFunctionCallback = |args, _| Ok(Str(str_arg(args, 0)?.to_lowercase()));
+
+const contains: NativeFunctionCallback =
+	|args, _| Ok(Bool(str_arg(args, 0)?.contains(str_arg(args, 1)?.as_str())));
+
+/// replace(s, from, to) replaces every occurrence
+const replace: NativeFunctionCallback = |args, _| {
+	let s = str_arg(args, 0)?;
+	let from = str_arg(args, 1)?;
+	let to = str_arg(args, 2)?;
+
+	if from.is_empty() {
+		return Err("The text to replace can't be empty".into());
+	}
+
+	Ok(Str(s.replace(from.as_str(), to)))
+};
+
+const starts_with: NativeFunctionCallback = |args, _| {
+	Ok(Bool(
+		str_arg(args, 0)?.starts_with(str_arg(args, 1)?.as_str()),
+	))
+};
+
+/// find(s, sub) returns the position of the first occurrence, or -1 if there isn't one
+const find: NativeFunctionCallback = |args, _| {
+	let s = str_arg(args, 0)?;
+	let sub = str_arg(args, 1)?;
+
+	let pos = match s.find(sub.as_str()) {
+		Some(byte_pos) => s[..byte_pos].chars().count() as i64,
+		None => -1,
+	};
+
+	Ok(Int(pos))
+};
+
+const chars: NativeFunctionCallback = |args, _| {
+	let s = str_arg(args, 0)?;
+	Ok(Object::list(
+		s.chars().map(|c| Str(c.to_string())).collect(),
+	))
+};
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("len", Arity::Exact(1), len),
+		("substr", Arity::Range(2, 3), substr),
+		("split", Arity::Range(1, 2), split),
+		("join", Arity::Range(1, 2), join),
+		("trim", Arity::Exact(1), trim),
+		("upper", Arity::Exact(1), upper),
+		("lower", Arity::Exact(1), lower),
+		("contains", Arity::Exact(2), contains),
+		("replace", Arity::Exact(3), replace),
+		("starts_with", Arity::Exact(2), starts_with),
+		("find", Arity::Exact(2), find),
+		("chars", Arity::Exact(1), chars),
+	]
+}
+
+/// The string functions that can be called as methods of strings, which is all but `join`
+pub(super) fn str_methods() -> Vec<Native> {
+	functions()
+		.into_iter()
+		.filter(|(k, _, _)| *k != "join")
+		.collect()
+}
+
+/// The string functions that also take lists, which lists have as methods
+pub(super) fn list_methods() -> Vec<Native> {
+	functions()
+		.into_iter()
+		.filter(|(k, _, _)| matches!(*k, "len" | "join"))
+		.collect()
+}
@@ -0,0 +1,251 @@
+//! Clock builtins and formatting of UTC timestamps, which are seconds since 1970-01-01.
+//! Formats use `%Y`, `%m`, `%d`, `%H`, `%M` and `%S` for the parts of the date and time,
+//! and `%%` for a `%`.
+use super::{number_arg, str_arg, Native};
+use crate::object::{Arity, NativeFunctionCallback, Object::*};
+use std::{
+	thread,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Where the time builtins get the time from. Hosts can use a fixed clock to control time
+/// in tests, which only moves when it's slept.
+#[derive(Debug, Clone)]
+pub enum Clock {
+	System {
+		start: Instant,
+	},
+	Fixed {
+		/// Seconds since 1970-01-01 UTC
+		wall: f64,
+		/// Seconds slept since the clock was made
+		elapsed: f64,
+	},
+}
+
+impl Clock {
+	pub fn system() -> Clock {
+		Clock::System {
+			start: Instant::now(),
+		}
+	}
+
+	/// A clock stopped at `wall` seconds since 1970-01-01 UTC
+	pub fn fixed(wall: f64) -> Clock {
+		Clock::Fixed {
+			wall: wall,
+			elapsed: 0.0,
+		}
+	}
+
+	/// Seconds since 1970-01-01 UTC
+	pub fn now(&self) -> f64 {
+		match self {
+			Clock::System { .. } => match SystemTime::now().duration_since(UNIX_EPOCH) {
+				Ok(v) => v.as_secs_f64(),
+				Err(e) => -e.duration().as_secs_f64(),
+			},
+			Clock::Fixed { wall, .. } => *wall,
+		}
+	}
+
+	/// Seconds since the clock was made, which never goes backwards
+	pub fn monotonic(&self) -> f64 {
+		match self {
+			Clock::System { start } => start.elapsed().as_secs_f64(),
+			Clock::Fixed { elapsed, .. } => *elapsed,
+		}
+	}
+
+	/// Waits for `seconds`, or moves a fixed clock forward by that much straight away.
+	/// Errors if it's negative or too long to wait for
+	pub fn sleep(&mut self, seconds: f64) -> Result<(), String> {
+		let duration = Duration::try_from_secs_f64(seconds)
+			.map_err(|_| format!("Can't sleep for {seconds} seconds"))?;
+		match self {
+			Clock::System { .. } => thread::sleep(duration),
+			Clock::Fixed { wall, elapsed } => {
+				*wall += seconds;
+				*elapsed += seconds;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Default for Clock {
+	fn default() -> Self {
+		Clock::system()
+	}
+}
+
+const ISO_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// The number of days from 1970-01-01 to the date, which is negative before it
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	// counts from 0000-03-01 so the leap day is at the end of each year
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
+/// The (year, month, day) that's `days` after 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+	let days = days + 719468;
+	let era = days.div_euclid(146097);
+	let day_of_era = days - era * 146097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let month = if month < 10 { month + 3 } else { month - 9 };
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+	days_from_civil(year + month / 12, month % 12 + 1, 1) - days_from_civil(year, month, 1)
+}
+
+fn format(timestamp: i64, format: &str) -> Result<String, String> {
+	let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+	let seconds = timestamp.rem_euclid(86400);
+
+	let mut res = String::new();
+	let mut chars = format.chars();
+	while let Some(c) = chars.next() {
+		if c != '%' {
+			res.push(c);
+			continue;
+		}
+		let part = match chars.next() {
+			Some('Y') => format!("{year:04}"),
+			Some('m') => format!("{month:02}"),
+			Some('d') => format!("{day:02}"),
+			Some('H') => format!("{:02}", seconds / 3600),
+			Some('M') => format!("{:02}", seconds / 60 % 60),
+			Some('S') => format!("{:02}", seconds % 60),
+			Some('%') => "%".to_string(),
+			Some(c) => return Err(format!("Unknown format %{c}")),
+			None => return Err("A format can't end with %".to_string()),
+		};
+		res.push_str(&part);
+	}
+	Ok(res)
+}
+
+/// Reads `text` written in `format` back into a timestamp
+fn parse(text: &str, format: &str) -> Result<i64, String> {
+	let invalid = || format!("Can't parse \"{text}\" as a time in the format \"{format}\"");
+
+	let (mut year, mut month, mut day) = (1970, 1, 1);
+	let (mut hour, mut minute, mut second) = (0, 0, 0);
+
+	let mut text = text.chars().peekable();
+	let mut chars = format.chars();
+	while let Some(c) = chars.next() {
+		// anything that isn't a part has to be written as it is
+		let part = match c {
+			'%' => chars.next().ok_or("A format can't end with %")?,
+			c => {
+				if text.next() != Some(c) {
+					return Err(invalid());
+				}
+				continue;
+			}
+		};
+		if part == '%' {
+			if text.next() != Some('%') {
+				return Err(invalid());
+			}
+			continue;
+		}
+
+		// years are up to four digits and can be negative, the rest are up to two digits
+		let mut digits = String::new();
+		if part == 'Y' && text.peek() == Some(&'-') {
+			digits.push('-');
+			text.next();
+		}
+		let width = if part == 'Y' { 4 } else { 2 };
+		while digits.trim_start_matches('-').len() < width {
+			match text.next_if(|v| v.is_ascii_digit()) {
+				Some(v) => digits.push(v),
+				None => break,
+			}
+		}
+		let value = digits.parse::<i64>().map_err(|_| invalid())?;
+
+		match part {
+			'Y' => year = value,
+			'm' => month = value,
+			'd' => day = value,
+			'H' => hour = value,
+			'M' => minute = value,
+			'S' => second = value,
+			c => return Err(format!("Unknown format %{c}")),
+		}
+	}
+
+	let valid = text.next().is_none()
+		&& (1..=12).contains(&month)
+		&& (1..=days_in_month(year, month)).contains(&day)
+		&& hour < 24
+		&& minute < 60
+		&& second < 60;
+	if !valid {
+		return Err(invalid());
+	}
+	Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Formats have a default, so they're the optional last argument
+fn format_arg(args: &[crate::object::Object], index: usize) -> Result<&str, String> {
+	match args.get(index) {
+		Some(_) => Ok(str_arg(args, index)?.as_str()),
+		None => Ok(ISO_FORMAT),
+	}
+}
+
+/// now() returns the seconds since 1970-01-01 UTC
+const now: NativeFunctionCallback = |_, i| Ok(Float(i.clock.now()));
+
+/// monotonic() returns seconds that only go forwards, for measuring how long things take
+const monotonic: NativeFunctionCallback = |_, i| Ok(Float(i.clock.monotonic()));
+
+/// sleep(ms) waits for that many milliseconds
+const sleep: NativeFunctionCallback = |args, i| {
+	let ms = number_arg(args, 0)?;
+	i.clock
+		.sleep(ms / 1000.0)
+		.map_err(|_| format!("Can't sleep for {ms} milliseconds"))?;
+	Ok(Nil)
+};
+
+/// format_time(timestamp, format) where the format defaults to ISO 8601 like
+/// `2024-01-31T12:00:00Z`. Fractions of a second are left out
+const format_time: NativeFunctionCallback = |args, _| {
+	let timestamp = number_arg(args, 0)?.floor();
+	if !(timestamp.abs() < 1e15) {
+		return Err(format!("Timestamp {timestamp} is out of range").into());
+	}
+	Ok(Str(format(timestamp as i64, format_arg(args, 1)?)?))
+};
+
+/// parse_time(text, format) returns the timestamp of a UTC time written in the format
+const parse_time: NativeFunctionCallback =
+	|args, _| Ok(Int(parse(str_arg(args, 0)?, format_arg(args, 1)?)?));
+
+pub(super) fn functions() -> Vec<Native> {
+	vec![
+		("now", Arity::Exact(0), now),
+		("monotonic", Arity::Exact(0), monotonic),
+		("sleep", Arity::Exact(1), sleep),
+		("format_time", Arity::Range(1, 2), format_time),
+		("parse_time", Arity::Range(1, 2), parse_time),
+	]
+}
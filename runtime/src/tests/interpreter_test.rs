@@ -114,3 +114,929 @@ fn scope() {
 	i.run().unwrap();
 	assert_eq!(i.stdout, "13\n".to_string())
 }
+
+#[test]
+fn string_operators() {
+	let src = r#"
+  var s = "ab" + "cd"
+  println(s)
+  println(s == "abcd", s != "abcd")
+  println("apple" < "banana", "b" > "a", "a" <= "a", "a" >= "b")
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"abcd\ntruefalse\ntruetruetruefalse\n"
+	);
+}
+
+#[test]
+fn indexing_and_slicing() {
+	let src = r#"
+  var s = "héllo"
+  println(s[1], s[1:3], s[:2], s[3:])
+  var l = [1, 2, 3]
+  l[0] = 10
+  println(l[0], l[1:])
+  "#;
+	assert_eq!(super::run(src).unwrap(), "éélhélo\n10[2, 3]\n");
+
+	assert!(super::run(r#"println("abc"[3])"#).is_err());
+	assert!(super::run(r#"println("abc"[2:1])"#).is_err());
+	assert!(super::run(r#"println("abc"[1.5])"#).is_err());
+}
+
+#[test]
+fn integers() {
+	let src = r#"
+  println(7 / 2, 0, -7 / 2, 0, 7 % 3, 0, -7 % 3)
+  println(3, 3.0, 2.5, 1 + 2.0, 6 / 4.0)
+  println(1 == 1.0, 2 < 2.5, -3)
+  println(9223372036854775807)
+  println(-9223372036854775808, " ", -2 ** 2, " ", 1 -1)
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"30-3010-1\n33.02.53.01.5\ntruetrue-3\n9223372036854775807\n-9223372036854775808 -4 0\n"
+	);
+
+	assert!(super::run("println(9223372036854775807 + 1)").is_err());
+	assert!(super::run("println(1 / 0)").is_err());
+	assert!(super::run("println(1 % 0)").is_err());
+	assert!(super::run("println(9223372036854775808)").is_err());
+	assert!(super::run("println(-9223372036854775809)").is_err());
+	assert!(super::run("println(-9223372036854775808 - 1)").is_err());
+	assert_eq!(super::run("println(1 / 0.0)").unwrap(), "inf\n");
+}
+
+#[test]
+fn operators() {
+	let src = r"
+  println(2 ** 3 ** 2, 0, -2 ** 2, 0, 2 ** -1, 0, 2.0 ** 0.5 > 1.41)
+  println(6 & 3, 0, 6 | 3, 0, 6 ^ 3, 0, 1 << 4, 0, -16 >> 2)
+  println(1 + 2 << 1, 0, 1 | 2 == 3, 0, true & false, true | false, true ^ true)
+  println(7.5 % 2, 0, 2 * 3 ** 2)
+  ";
+	assert_eq!(
+		super::run(src).unwrap(),
+		"5120-400.50true\n207050160-4\n60true0falsetruefalse\n1.5018\n"
+	);
+
+	assert!(super::run("println(2 ** 64)").is_err());
+	assert!(super::run("println(1 << 64)").is_err());
+	assert!(super::run("println(1.0 & 1)").is_err());
+}
+
+#[test]
+fn compound_assignment() {
+	let src = r#"
+  var x = 10
+  x += 5
+  x -= 3
+  x *= 2
+  x /= 5
+  println(x)
+  var s = "a"
+  s += "b"
+  println(s)
+  var l = [1, 2]
+  var calls = 0
+  fn index() {
+    calls += 1
+    return 1
+  }
+  l[index()] *= 10
+  println(l, calls)
+  "#;
+	assert_eq!(super::run(src).unwrap(), "4\nab\n[1, 20]1\n");
+
+	assert!(super::run("y += 1").is_err());
+}
+
+#[test]
+fn comparisons() {
+	let src = r#"
+  fn add(x, y) {
+    return x + y
+  }
+  println(1 <= 1, 2 >= 3, 1.5 >= 1, "a" <= "b", [1, 2] < [1, 3], [1] < [1, 0])
+  println(1 == "1", true == 1, [1] == [1.0], print == print, add == add, add == print)
+  var nan = 0.0 / 0.0
+  println(nan == nan, nan != nan, nan < 1, nan >= 1)
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"truefalsetruetruetruetrue\nfalsefalsetruetruetruefalse\nfalsetruefalsefalse\n"
+	);
+
+	assert!(super::run("println(true < false)").is_err());
+	assert!(super::run(r#"println("a" < 1)"#).is_err());
+	assert!(super::run(r#"println([1] <= ["a"])"#).is_err());
+	assert!(super::run("println(print > print)").is_err());
+
+	// lists that contain themselves are equal once the comparison comes back around
+	let src = "var a = [0]\na[0] = a\nvar b = [0]\nb[0] = b\nprintln(a == b, a == [b], a != [1])";
+	assert_eq!(super::run(src).unwrap(), "truetruetrue\n");
+	assert_eq!(
+		super::run(&format!("{src}\nprintln(a < b)")).unwrap_err(),
+		"(Ln 6, Col 0) Can't compare list with list"
+	);
+}
+
+#[test]
+fn display_every_type() {
+	let src = r#"
+  fn add(x, y) {
+    return x + y
+  }
+  fn nothing() {
+  }
+  println(add, " ", print, " ", nothing())
+  println(["a", 1, 2.0, [true]], " ", repr("a"), " ", repr(1))
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"<fn add(x, y)> <native print> nil\n[\"a\", 1, 2.0, [true]] \"a\" 1\n"
+	);
+}
+
+#[test]
+fn eval() {
+	let mut i = Interpreter::default();
+	assert_eq!(i.eval("var x = 1").unwrap(), None);
+	assert_eq!(i.eval("x + 1").unwrap(), Some("2".to_string()));
+	assert_eq!(i.eval(r#""a" + "b""#).unwrap(), Some("\"ab\"".to_string()));
+	assert_eq!(i.eval("print").unwrap(), Some("<native print>".to_string()));
+	assert_eq!(i.eval("println(x)").unwrap(), None);
+	assert!(i.eval("while true { y }").is_err());
+	// the failed loop's scope doesn't leak into later input
+	assert_eq!(i.eval("var y = 3").unwrap(), None);
+	assert_eq!(i.eval("{ var y = 4 } y").unwrap(), Some("3".to_string()));
+}
+
+#[test]
+fn for_loops() {
+	let src = r#"
+  for i in range(3) {
+    print(i)
+  }
+  for i in range(10, 0, -3) {
+    print(" ", i)
+  }
+  println("")
+  for c in "hé" {
+    print(c, ".")
+  }
+  var total = 0
+  for v in [1, 2, 3] {
+    var doubled = v * 2
+    total += doubled
+  }
+  println(total, " ", range(1, 4))
+  fn first_over(limit, items) {
+    for v in items {
+      while v > limit {
+        return v
+      }
+    }
+    return -1
+  }
+  println(first_over(2, [1, 5, 7]), " ", first_over(9, [1]))
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"012 10 7 4 1\nh.é.12 range(1, 4, 1)\n5 -1\n"
+	);
+
+	assert!(super::run("for i in 5 { }").is_err());
+	assert!(super::run("for i in range(0, 5, 0) { }").is_err());
+	// the loop variable only exists inside the loop
+	assert!(super::run("for i in range(2) { }\nprintln(i)").is_err());
+}
+
+#[test]
+fn structs() {
+	let src = r#"
+  struct Point { x, y }
+  var a = Point { y: 2, x: 1 }
+  var b = Point(1, 2)
+  println(a, " ", a == b, " ", Point)
+  var c = a
+  c.x = 10
+  c.y += 5
+  println(a.x, " ", a.y, " ", a == b)
+  fn move(p) {
+    p.x = 0
+  }
+  move(b)
+  println(b.x)
+  struct Line { from, to }
+  var line = Line(Point(0, 0), Point(3, 4))
+  line.to.x = 6
+  println(line.to.x)
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"Point { x: 1, y: 2 } true <struct Point>\n10 7 false\n0\n6\n"
+	);
+
+	let errors = [
+		"struct P { x }\nvar p = P(1)\nprintln(p.y)",
+		"struct P { x }\nvar p = P(1)\np.y = 2",
+		"struct P { x }\nvar p = P { x: 1, y: 2 }",
+		"struct P { x, y }\nvar p = P { x: 1 }",
+		"struct P { x }\nvar p = P { x: 1, x: 2 }",
+		"struct P { x }\nvar p = P(1, 2)",
+		"struct P { x, x }",
+		"var p = 1\nprintln(p.x)",
+	];
+	for src in errors {
+		assert!(super::run(src).is_err(), "{src}");
+	}
+}
+
+#[test]
+fn calling_values() {
+	let src = r#"
+  fn add(a, b = 1) {
+    return a + b
+  }
+  fn make() {
+    return add
+  }
+  struct P { f }
+  var fns = [add]
+  var p = P(add)
+  println(make()(1), " ", fns[0](1, b: 2), " ", (p.f)(3))
+  "#;
+	assert_eq!(super::run(src).unwrap(), "2 3 4\n");
+	assert_eq!(
+		super::run("fn make() {\n  return 1\n}\nmake()(2)").unwrap_err(),
+		"(Ln 4, Col 0) int is not a function"
+	);
+}
+
+#[test]
+fn methods() {
+	let src = r#"
+  struct Point { x, y }
+  impl Point {
+    fn len_squared(self) {
+      return self.x * self.x + self.y * self.y
+    }
+    fn scale(self, by) {
+      self.x *= by
+      self.y *= by
+    }
+    fn origin() {
+      return Point(0, 0)
+    }
+  }
+  var p = Point(3, 4)
+  p.scale(2)
+  println(p.len_squared(), " ", Point.origin())
+  println("abc".upper(), " ", " x ".trim().len(), " ", "a,b".split(",").join("-"))
+  println([1, 2, 3].len(), " ", [1, 2].map(repr).list(), " ", range(3).list(), " ", 1.repr())
+  println((-7).abs(), " ", 2.5.floor(), " ", 9.sqrt(), " ", 3.clamp(0, 2))
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"100 Point { x: 0, y: 0 }\nABC 1 a-b\n3 [\"1\", \"2\"] [0, 1, 2] 1\n7 2 3.0 2\n"
+	);
+
+	assert!(super::run("struct P { x }\nvar p = P(1)\np.nope()").is_err());
+	assert!(super::run("\"abc\".nope()").is_err());
+	assert!(super::run("1.upper()").is_err());
+	assert!(super::run("impl Nope { }").is_err());
+	// each type only has the methods that take it
+	assert_eq!(
+		super::run("[1].upper()").unwrap_err(),
+		"(Ln 1, Col 1) list has no method `upper`"
+	);
+	assert_eq!(
+		super::run("\"a\".join()").unwrap_err(),
+		"(Ln 1, Col 1) string has no method `join`"
+	);
+}
+
+#[test]
+fn instance_iterables() {
+	let src = r#"
+  struct Counter { n, limit }
+  impl Counter {
+    fn next(self) {
+      while self.n < self.limit {
+        self.n += 1
+        return self.n
+      }
+    }
+  }
+  for v in Counter(0, 3) {
+    print(v)
+  }
+  println("")
+  println(list(map(Counter(0, 2), repr)))
+  "#;
+	assert_eq!(super::run(src).unwrap(), "123\n[\"1\", \"2\"]\n");
+}
+
+#[test]
+fn nil() {
+	let src = r#"
+  fn nothing() {
+  }
+  var x = nil
+  println(x, " ", nothing(), " ", nothing() == nil, " ", nil == 0, " ", nil != false, " ", [nil])
+  println(x ?? "default", " ", 0 ?? 1, " ", nil ?? nil ?? 3)
+  struct Node { value, next }
+  var list = Node(1, Node(2, nil))
+  println(list.next?.value, " ", list.next.next?.value, " ", list.next.next?.value ?? "end")
+  println(x?.upper(), " ", "a"?.upper())
+  var calls = 0
+  fn count() {
+    calls += 1
+    return 1
+  }
+  var y = 5 ?? count()
+  println(calls)
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"nil nil true false true [nil]\ndefault 0 3\n2 nil end\nnil A\n0\n"
+	);
+
+	assert!(super::run("println(nil < 1)").is_err());
+	assert!(super::run("var x = nil\nprintln(x.field)").is_err());
+	assert!(super::run("println(nil + 1)").is_err());
+}
+
+#[test]
+fn try_catch() {
+	let src = r#"
+  try {
+    var x = 1 / 0
+  } catch e {
+    println(e.message, " ", e.line, " ", e.column)
+  }
+  try {
+    throw [1, 2]
+  } catch e {
+    println(e)
+  }
+  fn risky(n) {
+    try {
+      while n > 0 {
+        throw error("too big")
+      }
+      return "ok"
+    } finally {
+      print("finally ")
+    }
+  }
+  println(risky(0))
+  try {
+    risky(1)
+  } catch e {
+    println(e.message)
+  }
+  try {
+    try {
+      throw "inner"
+    } catch e {
+      throw e + " again"
+    }
+  } catch e {
+    println(e)
+  }
+  fn inverse(x) {
+    return 1 / x
+  }
+  try {
+    for v in map([1, 0], inverse) {
+      print(v, " ")
+    }
+  } catch e {
+    println(e.message)
+  }
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"Integer division by zero in 1 Slash 0 3 4\n[1, 2]\nfinally ok\nfinally too big\ninner again\n1 Integer division by zero in 1 Slash 0\n"
+	);
+
+	// errors point at the statement that failed, even inside a function
+	let src = "fn half(n) {\n  var a = 1\n  return n / 0\n}\n";
+	assert_eq!(
+		super::run(&format!(
+			"{src}try {{\n  half(1)\n}} catch e {{\n  println(e.line, \" \", e.column)\n}}"
+		))
+		.unwrap(),
+		"3 2\n"
+	);
+	assert_eq!(
+		super::run(&format!("{src}half(2)")).unwrap_err(),
+		"(Ln 3, Col 2) Integer division by zero in 2 Slash 0"
+	);
+
+	assert_eq!(
+		super::run("throw \"oops\"").unwrap_err(),
+		"(Ln 1, Col 1) Uncaught \"oops\""
+	);
+	assert!(super::run("throw error(\"bad\")")
+		.unwrap_err()
+		.ends_with(") bad"));
+	// only the errors the interpreter makes are shown as messages
+	assert_eq!(
+		super::run("struct Error { message }\nthrow Error(\"mine\")").unwrap_err(),
+		"(Ln 2, Col 0) Uncaught Error { message: \"mine\" }"
+	);
+	assert!(super::run("try { }").is_err());
+}
+
+#[test]
+fn results() {
+	let src = r#"
+  fn parse_digit(s) {
+    var digits = "0123456789"
+    var i = digits.find(s)
+    while i < 0 {
+      return err("not a digit: " + s)
+    }
+    return ok(i)
+  }
+  fn sum_digits(s) {
+    var total = 0
+    for c in s {
+      {
+        total += parse_digit(c)?
+      }
+    }
+    return ok(total)
+  }
+  println(sum_digits("123"), " ", sum_digits("1x3"))
+  var r = sum_digits("99")
+  println(is_ok(r), " ", r.unwrap(), " ", unwrap_or(sum_digits("?"), 0), " ", r.is_ok())
+  println(ok(1) == ok(1), " ", ok(1) == err(1), " ", err("a"))
+  try {
+    unwrap(err("bad"))
+  } catch e {
+    println(e.message)
+  }
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"ok(6) err(\"not a digit: x\")\ntrue 18 0 true\ntrue false err(\"a\")\nCalled unwrap on err(\"bad\")\n"
+	);
+
+	assert!(super::run("fn f() { return 1? }\nf()").is_err());
+	assert!(super::run("err(1)?")
+		.unwrap_err()
+		.ends_with("`?` returned err(1) outside of a function"));
+	assert_eq!(super::run("println(ok(2)?)").unwrap(), "2\n");
+}
+
+/// Writes `files` into a new directory under the temp directory and returns its path
+fn write_files(dir: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+	let dir = std::env::temp_dir().join(dir);
+	let _ = std::fs::remove_dir_all(&dir);
+
+	for (name, src) in files {
+		let path = dir.join(name);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, src).unwrap();
+	}
+	dir
+}
+
+fn run_file(path: std::path::PathBuf) -> Result<String, String> {
+	let mut i = Interpreter::from_file(path)?;
+	i.run()?;
+	Ok(i.stdout)
+}
+
+#[test]
+fn imports() {
+	let dir = write_files(
+		"simple_imports_test",
+		&[
+			(
+				"main.txt",
+				r#"
+  import "lib/math.txt" as math
+  from "lib/math.txt" import square, scale
+  var factor = 100
+  println(math.square(3), " ", square(4), " ", scale, " ", math.twice(5), " ", math)
+  math.bump()
+  println(math.counter, " ", math.pi.value)
+  import "lib/math.txt" as again
+  println(again.counter, " ", again == math)
+  "#,
+			),
+			(
+				"lib/math.txt",
+				r#"
+  import "consts.txt" as pi
+  println("loading math")
+  var scale = 2
+  var counter = 0
+  fn square(x) {
+    return x * x
+  }
+  fn twice(x) {
+    return x * scale
+  }
+  fn bump() {
+    counter += 1
+  }
+  "#,
+			),
+			("lib/consts.txt", "var value = 3"),
+		],
+	);
+	assert_eq!(
+		run_file(dir.join("main.txt")).unwrap(),
+		"loading math\n9 16 2 10 <module lib/math.txt>\n1 3\n1 true\n"
+	);
+}
+
+#[test]
+fn import_errors() {
+	let dir = write_files(
+		"simple_import_errors_test",
+		&[
+			("a.txt", "import \"b.txt\" as b"),
+			("b.txt", "from \"a.txt\" import x"),
+			("missing.txt", "import \"nope.txt\" as nope"),
+			("names.txt", "from \"c.txt\" import nope"),
+			("c.txt", "var x = 1"),
+			("private.txt", "import \"c.txt\" as c\nprintln(c.println)"),
+			(
+				"caught.txt",
+				"try {\n  from \"c.txt\" import y\n} catch e {\n  println(e.message)\n}",
+			),
+		],
+	);
+
+	let dir = dir.canonicalize().unwrap();
+	let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+	assert_eq!(
+		run_file(a.clone()).unwrap_err(),
+		format!(
+			"(Ln 1, Col 1) Import cycle: {} -> {} -> {}",
+			a.display(),
+			b.display(),
+			a.display()
+		)
+	);
+	assert!(run_file(dir.join("missing.txt"))
+		.unwrap_err()
+		.contains("Can't import \"nope.txt\""));
+	assert!(run_file(dir.join("names.txt"))
+		.unwrap_err()
+		.ends_with("Module \"c.txt\" has no `nope`"));
+	assert!(run_file(dir.join("private.txt")).is_err());
+	assert_eq!(
+		run_file(dir.join("caught.txt")).unwrap(),
+		"Module \"c.txt\" has no `y`\n"
+	);
+}
+
+#[test]
+fn constants() {
+	let src = r#"
+  const LIMIT = 100
+  {
+    var LIMIT = 5
+    LIMIT += 1
+    println(LIMIT)
+  }
+  fn shadow(LIMIT) {
+    LIMIT = LIMIT * 2
+    return LIMIT
+  }
+  const items = [1]
+  items[0] = 2
+  println(LIMIT, " ", shadow(3), " ", items)
+  "#;
+	assert_eq!(super::run(src).unwrap(), "6\n100 6 [2]\n");
+
+	// caught before running
+	assert_eq!(
+		super::check("const X = 1\nprintln(X)\nX = 2").unwrap_err(),
+		"(Ln 3, Col 2) Can't assign to constant `X`"
+	);
+	assert!(super::check("const X = 1\nwhile true {\n  X += 1\n}").is_err());
+	assert!(super::check("const X = 1\nvar X = 2").is_err());
+	assert!(super::check("const X = 1\nfn f() {\n  X = 2\n}").is_err());
+	assert!(super::check("{\n  const X = 1\n}\nvar X = 2\nX = 3").is_ok());
+
+	// only seen while running
+	let mut i = Interpreter::default();
+	i.eval("const X = 1").unwrap();
+	assert_eq!(
+		i.eval("X = 2").unwrap_err().to_string(),
+		"(Ln 1, Col 1) Can't assign to constant `X`"
+	);
+	assert_eq!(i.eval("X").unwrap(), Some("1".to_string()));
+	assert!(super::run("fn set() {\n  X = 2\n}\nconst X = 1\nset()").is_err());
+}
+
+#[test]
+fn declarations() {
+	// (source, output or None for an error)
+	let cases = [
+		// blocks shadow outer variables and assign to them when not shadowing
+		(
+			"var x = 1\n{\n  var x = 2\n  print(x)\n}\nprint(x)",
+			Some("21"),
+		),
+		("var x = 1\n{\n  x = 2\n}\nprint(x)", Some("2")),
+		("var x = 1\nvar x = 2", None),
+		("{\n  var x = 1\n  var x = 2\n}", None),
+		("{\n  var x = 1\n}\nprint(x)", None),
+		// loop bodies get a new scope each iteration
+		(
+			"var i = 0\nwhile i < 3 {\n  var x = i\n  i += 1\n  print(x)\n}",
+			Some("012"),
+		),
+		(
+			"for i in range(3) {\n  var x = i * 2\n  print(x)\n}",
+			Some("024"),
+		),
+		("for i in range(2) {\n  var i = 5\n}", None),
+		(
+			"var i = 0\nwhile i < 1 {\n  i += 1\n  var y = 1\n}\nprint(y)",
+			None,
+		),
+		// functions have their own scope for parameters and locals
+		(
+			"var x = 1\nfn f() {\n  var x = 2\n  return x\n}\nprint(f(), f(), x)",
+			Some("221"),
+		),
+		("fn f(x) {\n  var x = 2\n}", None),
+		("fn f() { }\nfn f() { }", None),
+		("fn f() { }\nvar f = 1", None),
+		("struct P { x }\nvar P = 1", None),
+		// the caught value is outside of the handler's block
+		(
+			"try {\n  throw 1\n} catch e {\n  var e = e + 1\n  print(e)\n}",
+			Some("2"),
+		),
+	];
+
+	for (src, expected) in cases {
+		match expected {
+			Some(v) => assert_eq!(super::run(src).unwrap(), v, "{src}"),
+			None => assert!(super::run(src).is_err(), "{src}"),
+		}
+	}
+
+	// the top level of `eval` can declare names again, so a REPL can redefine them
+	let mut i = Interpreter::default();
+	i.eval("var x = 1").unwrap();
+	i.eval("var x = x + 1").unwrap();
+	assert_eq!(i.eval("x").unwrap(), Some("2".to_string()));
+	i.eval("const C = 1").unwrap();
+	i.eval("var C = 2\nC = 3").unwrap();
+	assert_eq!(i.eval("C").unwrap(), Some("3".to_string()));
+	i.eval("fn f() {\n  var y = 1\n  return y\n}").unwrap();
+	assert_eq!(i.eval("f() + f()").unwrap(), Some("2".to_string()));
+	i.eval("fn f() {\n  return 5\n}").unwrap();
+	assert_eq!(i.eval("f()").unwrap(), Some("5".to_string()));
+	// but not inside blocks
+	assert_eq!(
+		i.eval("{\n  var y = 1\n  {\n    var z = 1\n  }\n  var y = 2\n}")
+			.unwrap_err()
+			.to_string(),
+		"(Ln 1, Col 1) `y` is already declared in this scope"
+	);
+}
+
+#[test]
+fn type_annotations() {
+	let src = r#"
+  struct Point { x, y }
+  fn add(a: number, b: number) -> number {
+    return a + b
+  }
+  fn origin() -> Point {
+    return Point(0, 0)
+  }
+  var total: number = add(1, 2.5)
+  const name: string = "total"
+  var maybe = nil
+  maybe = 1
+  var p: Point = origin()
+  println(name, " ", total, " ", p.x, " ", maybe + 1)
+  "#;
+	assert_eq!(super::run(src).unwrap(), "total 3.5 0 2\n");
+
+	let check = |src| super::check(src).err();
+	// every mismatch with an annotation is reported, positioned at the expression or
+	// statement it's in
+	let src = "var x: int = \"a\"
+fn add(a: number, b: number) -> number {
+  var s = \"s\"
+  return s
+}
+add(1, \"2\")
+var y = add(1, 2) + \"!\"
+var count: int = 0
+count = count - [1]
+x = 2.5
+while x { }";
+	assert_eq!(
+		check(src).unwrap(),
+		"(Ln 1, Col 1) `x` should be int, instead got string
+(Ln 4, Col 2) `add` should return number, instead got string
+(Ln 6, Col 0) Argument 2 of `add` should be number, instead got string
+(Ln 7, Col 18) Unsupported operation Plus between number and string
+(Ln 9, Col 14) Unsupported operation Minus between int and list
+(Ln 10, Col 2) `x` should be int, instead got float
+(Ln 11, Col 0) Expression after while should be bool, instead got int"
+	);
+
+	// unannotated code is left to be checked while running, where its errors can be caught
+	let fine = [
+		"var x = \"a\" + 1",
+		"while false {\n  1 + \"a\"\n}",
+		"while 1 { }",
+		"var x = 1\nx()",
+		"fn f(a: int) { }\nf(1, 2)",
+		"var x = 1\nx = \"a\"\nprintln(x + \"b\")",
+		"var x = 1\nfn f() {\n  return x + \"a\"\n}",
+		"fn f(a) {\n  return a + 1\n}\nf(\"a\")",
+		"var x: any = 1\nx = \"a\"",
+		"var n: number = 1\nn = 1.5",
+		"var xs = [1]\nprintln(xs[0] + \"a\")",
+	];
+	for src in fine {
+		assert_eq!(check(src), None, "{src}");
+	}
+
+	// loops see what their earlier iterations assigned
+	let src = "var last = nil
+for v in range(3) {
+  while last != nil {
+    println(last * 2)
+    last = nil
+  }
+  last = v
+}";
+	assert_eq!(super::run(src).unwrap(), "0\n2\n");
+	assert_eq!(
+		check("var i = 0\nwhile i < 3 {\n  i += 1\n  var s: string = i\n}"),
+		Some("(Ln 4, Col 2) `s` should be string, instead got int".to_string())
+	);
+	assert_eq!(
+		check("var x: int = 1\nx()"),
+		Some("(Ln 2, Col 0) `x` is int, not a function".to_string())
+	);
+	assert_eq!(
+		super::run("try {\n  var x = \"a\" + 1\n} catch e {\n  println(e.message)\n}").unwrap(),
+		"Unsupported operation Plus between string and int\n"
+	);
+}
+
+#[test]
+fn parameters() {
+	let src = r#"
+  fn greet(name, greeting = "hello", end = greeting + "!") {
+    return greeting + " " + name + end
+  }
+  fn sum(first, ...rest) {
+    var total = first
+    for v in rest {
+      total += v
+    }
+    return total
+  }
+  struct Box { size }
+  impl Box {
+    fn grow(self, by = 1, times = 1) {
+      self.size += by * times
+      return self.size
+    }
+  }
+  var b = Box(1)
+  println(greet("ann"), " ", greet("bo", end: "?"), " ", greet(greeting: "hi", name: "cy"))
+  println(sum(1), " ", sum(1, 2, 3), " ", sum)
+  println(b.grow(), " ", b.grow(times: 2, by: 3))
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"hello annhello! hello bo? hi cyhi!\n1 6 <fn sum(first, ...rest)>\n2 8\n"
+	);
+
+	// arguments to annotated parameters are checked before running
+	let check = |src| super::check(src).err();
+	let src = "fn f(a, b: int = 2) { }
+f(1, b: \"2\")
+fn g(a: int = \"1\") { }";
+	assert_eq!(
+		check(src).unwrap(),
+		"(Ln 2, Col 0) Argument `b` of `f` should be int, instead got string
+(Ln 3, Col 0) Default of parameter `a` should be int, instead got string"
+	);
+	assert_eq!(
+		check("fn f(a: int) { }\nprintln(1, f(\"a\"))").unwrap(),
+		"(Ln 2, Col 11) Argument 1 of `f` should be int, instead got string"
+	);
+
+	// and how many there are while running
+	let src = "fn f(a, b: int = 2) { }\n";
+	let run = |call| super::run(&format!("{src}{call}")).unwrap_err();
+	assert_eq!(
+		run("f()"),
+		"(Ln 2, Col 0) `f` is missing an argument for parameter `a`"
+	);
+	assert_eq!(
+		run("f(1, 2, 3)"),
+		"(Ln 2, Col 0) `f` takes at most 2 arguments, but 3 were given"
+	);
+	assert_eq!(
+		super::run("fn g(a) { }\ng(1, 2)").unwrap_err(),
+		"(Ln 2, Col 0) `g` takes at most 1 argument, but 2 were given"
+	);
+	assert_eq!(
+		run("f(1, c: 2)"),
+		"(Ln 2, Col 0) `f` has no parameter named `c`"
+	);
+	assert_eq!(
+		run("f(1, a: 2)"),
+		"(Ln 2, Col 0) Parameter `a` of `f` is given more than once"
+	);
+	assert!(check("fn f(...rest, a) { }").is_some());
+	assert!(check("fn f(...rest = 1) { }").is_some());
+	assert!(check("f(a: 1, 2)").is_some());
+
+	// methods are only checked while running
+	let src = "struct P { x }\nimpl P {\n  fn m(self, a) { }\n}\nvar p = P(1)\n";
+	let run = |call| super::run(&format!("{src}{call}")).unwrap_err();
+	assert_eq!(
+		run("p.m()"),
+		"(Ln 6, Col 0) `m` is missing an argument for parameter `a`"
+	);
+	assert_eq!(
+		run("p.m(1, 2)"),
+		"(Ln 6, Col 0) `m` takes at most 1 argument, but 2 were given"
+	);
+	assert_eq!(
+		run("p.m(b: 1)"),
+		"(Ln 6, Col 0) `m` has no parameter named `b`"
+	);
+	assert_eq!(
+		run("p.m(1, a: 1)"),
+		"(Ln 6, Col 0) Parameter `a` of `m` is given more than once"
+	);
+	assert_eq!(
+		super::run("len(x: [1])").unwrap_err(),
+		"(Ln 1, Col 1) `len` doesn't take named arguments"
+	);
+	assert_eq!(
+		super::run("\"a\".contains(sub: \"b\")").unwrap_err(),
+		"(Ln 1, Col 1) `contains` doesn't take named arguments"
+	);
+}
+
+#[test]
+fn maps() {
+	let src = r#"
+  var m = new_map()
+  m["b"] = 1
+  m["a"] = [2]
+  m["b"] += 10
+  m["a"][0] = 3
+  var copy = m
+  copy["c"] = nil
+  println(m, " ", len(m), " ", m["b"], " ", m.keys(), " ", values(m))
+  for k in m {
+    print(k)
+  }
+  println(" ", has_key(m, "a"), " ", m.has_key("z"), " ", m.remove_key("c"), " ", m.remove_key("c"))
+  var n: map = new_map()
+  n["a"] = [3]
+  n["b"] = 11
+  println(m == n)
+  n["c"] = 1
+  n.remove_key("a")
+  n["c"] += 1
+  n["a"] = 0
+  println(n, " ", n["b"], " ", n["c"])
+  "#;
+	assert_eq!(
+		super::run(src).unwrap(),
+		"{\"b\": 11, \"a\": [3], \"c\": nil} 3 11 [\"b\", \"a\", \"c\"] [11, [3], nil]
+bac true false nil nil
+true
+{\"b\": 11, \"c\": 2, \"a\": 0} 11 2
+"
+	);
+
+	assert_eq!(
+		super::run("var m = new_map()\nm[\"x\"]").unwrap_err(),
+		"(Ln 2, Col 0) The map has no key \"x\""
+	);
+	assert!(super::run("var m = new_map()\nm[\"x\"] += 1").is_err());
+	assert!(super::run("var m = new_map()\nm[1] = 1").is_err());
+	assert!(super::run("keys([])").is_err());
+}
@@ -80,3 +80,60 @@ fn comment() {
 	dbg!(&res);
 	let it = res.iter();
 }
+
+#[test]
+fn after_comment() {
+	// the token after a comment starts where it's written
+	let res = Lexer::new("# a {\n{\n  n # c\nx").parse().unwrap();
+	let tokens: Vec<(&str, usize, usize)> =
+		res.iter().map(|t| (t.text.as_str(), t.ln, t.col)).collect();
+	assert_eq!(
+		tokens,
+		vec![
+			(" a {", 1, 2),
+			("{", 2, 0),
+			("n", 3, 2),
+			(" c", 3, 5),
+			("x", 4, 0),
+			("", 4, 1)
+		]
+	);
+}
+
+#[test]
+fn identifiers() {
+	let src = "starts_with x2 _tmp";
+	let res = Lexer::new(src).parse().unwrap();
+	let texts: Vec<&str> = res.iter().map(|t| t.text.as_str()).collect();
+	assert_eq!(texts, vec!["starts_with", "x2", "_tmp", ""]);
+}
+
+#[test]
+fn operator_kinds() {
+	use crate::token::TokenKind::*;
+	let src = "** *= += -= /= & | ^ << >> <= %";
+	let kinds: Vec<_> = Lexer::new(src)
+		.parse()
+		.unwrap()
+		.into_iter()
+		.map(|t| t.kind)
+		.collect();
+	assert_eq!(
+		kinds,
+		vec![
+			StarStar,
+			StarAssign,
+			PlusAssign,
+			MinusAssign,
+			SlashAssign,
+			Ampersand,
+			Pipe,
+			Caret,
+			ShiftLeft,
+			ShiftRight,
+			LessEquals,
+			Percent,
+			EOF
+		]
+	);
+}
@@ -1,3 +1,23 @@
 mod interpreter_test;
 mod lexer_test;
+mod object_test;
 mod parser_test;
+mod stdlib_test;
+
+use crate::{interpreter::Interpreter, lexer::Lexer, parser::Parser};
+
+/// Runs `src` and returns everything it printed
+pub(crate) fn run(src: &str) -> Result<String, String> {
+	let toks = Lexer::new(src).parse()?;
+	let ast = Parser::new(toks).parse()?;
+	let mut i = Interpreter::new(ast);
+	i.run()?;
+	Ok(i.stdout)
+}
+
+/// Checks `src` without running it
+pub(crate) fn check(src: &str) -> Result<(), String> {
+	let toks = Lexer::new(src).parse()?;
+	let ast = Parser::new(toks).parse()?;
+	crate::interpreter::check(&ast)
+}
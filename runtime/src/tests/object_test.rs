@@ -0,0 +1,181 @@
+use crate::object::{
+	Arity, FunctionDef, Instance as InstanceDef, Module as ModuleDef, NativeFunctionDef, Object,
+	Object::*, StructDef,
+};
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+/// One value of every kind, where only the pairs listed in `equal_pairs` are equal to each other
+fn samples() -> Vec<Object> {
+	let function = FunctionDef {
+		name: "f".to_string(),
+		params: vec![],
+		body: vec![],
+		globals: Default::default(),
+	};
+	let native = NativeFunctionDef {
+		name: "n".to_string(),
+		arity: Arity::Variadic(0),
+		callback: |_, _| Ok(Nil),
+	};
+
+	let point = Rc::new(StructDef {
+		name: "Point".to_string(),
+		fields: vec!["x".to_string()],
+		methods: Default::default(),
+	});
+	let instance = InstanceDef {
+		def: point.clone(),
+		fields: vec![Int(1)],
+	};
+
+	vec![
+		Int(1),
+		Float(1.0),
+		Float(2.5),
+		Float(f64::NAN),
+		Str("1".to_string()),
+		Str("b".to_string()),
+		Bool(true),
+		Bool(false),
+		Object::list(vec![Int(1)]),
+		Object::list(vec![Float(1.0)]),
+		Object::list(vec![Int(1), Int(2)]),
+		Object::map(vec![("a".to_string(), Int(1)), ("b".to_string(), Nil)]),
+		// the same entries in another order
+		Object::map(vec![("b".to_string(), Nil), ("a".to_string(), Float(1.0))]),
+		Object::map(vec![("a".to_string(), Int(1))]),
+		Range {
+			start: 0,
+			end: 3,
+			step: 1,
+		},
+		Object::from_iter(std::iter::empty()),
+		Function(Rc::new(function.clone())),
+		Function(Rc::new(function)),
+		NativeFunction(Rc::new(native)),
+		Struct(point),
+		Instance(Rc::new(RefCell::new(instance))),
+		Module(Rc::new(ModuleDef {
+			path: "lib.txt".to_string(),
+			globals: Default::default(),
+		})),
+		Nil,
+	]
+}
+
+fn equal_pairs() -> Vec<(usize, usize)> {
+	vec![(0, 1), (8, 9), (11, 12)]
+}
+
+fn is_ordered(v: &Object) -> bool {
+	match v {
+		Int(_) | Str(_) | List(_) => true,
+		Float(v) => !v.is_nan(),
+		_ => false,
+	}
+}
+
+#[test]
+fn equality_matrix() {
+	let samples = samples();
+	let nan = 3;
+
+	for (i, l) in samples.iter().enumerate() {
+		for (j, r) in samples.iter().enumerate() {
+			let expected = (i == j && i != nan)
+				|| equal_pairs().contains(&(i, j))
+				|| equal_pairs().contains(&(j, i));
+			assert_eq!(l == r, expected, "{l:?} == {r:?}");
+			assert_eq!(l != r, !expected, "{l:?} != {r:?}");
+		}
+	}
+}
+
+#[test]
+fn ordering_matrix() {
+	let samples = samples();
+
+	for l in samples.iter() {
+		for r in samples.iter() {
+			let same_kind = std::mem::discriminant(l) == std::mem::discriminant(r)
+				|| matches!((l, r), (Int(_) | Float(_), Int(_) | Float(_)));
+			let expected = same_kind && is_ordered(l) && is_ordered(r);
+			assert_eq!(
+				l.partial_cmp(r).is_some(),
+				expected,
+				"{l:?} compared with {r:?}"
+			);
+		}
+	}
+}
+
+#[test]
+fn orderings() {
+	let list = |v: Vec<Object>| Object::list(v);
+
+	assert_eq!(Int(1).partial_cmp(&Float(1.5)), Some(Ordering::Less));
+	assert_eq!(Float(-2.5).partial_cmp(&Int(-2)), Some(Ordering::Less));
+	assert_eq!(
+		Int(i64::MAX).partial_cmp(&Float(9.3e18)),
+		Some(Ordering::Less)
+	);
+	assert_ne!(Int(i64::MAX - 1), Float(i64::MAX as f64));
+	assert_eq!(
+		Str("apple".to_string()).partial_cmp(&Str("banana".to_string())),
+		Some(Ordering::Less)
+	);
+	assert_eq!(
+		list(vec![Int(1), Int(3)]).partial_cmp(&list(vec![Int(2)])),
+		Some(Ordering::Less)
+	);
+	assert_eq!(
+		list(vec![Int(1)]).partial_cmp(&list(vec![Int(1), Int(0)])),
+		Some(Ordering::Less)
+	);
+	assert_eq!(
+		list(vec![Int(1)]).partial_cmp(&list(vec![Str("a".to_string())])),
+		None
+	);
+}
+
+#[test]
+fn containing_itself() {
+	let list = Object::list(vec![Int(1)]);
+	if let List(v) = &list {
+		v.borrow_mut().push(list.clone());
+	}
+	assert_eq!(list.to_string(), "[1, [...]]");
+	assert_eq!(Object::list(vec![list.clone()]).repr(), "[[1, [...]]]");
+	assert!(list == list.clone());
+
+	let node = Rc::new(StructDef {
+		name: "Node".to_string(),
+		fields: vec!["next".to_string()],
+		methods: Default::default(),
+	});
+	let instance = Instance(Rc::new(RefCell::new(InstanceDef {
+		def: node,
+		fields: vec![Nil],
+	})));
+	if let Instance(v) = &instance {
+		v.borrow_mut().fields[0] = instance.clone();
+	}
+	assert_eq!(instance.to_string(), "Node { next: Node { ... } }");
+	assert!(instance == instance.clone());
+
+	let map = Object::map(vec![("a".to_string(), Int(1))]);
+	if let Map(v) = &map {
+		v.borrow_mut().insert("self".to_string(), map.clone());
+	}
+	assert_eq!(map.to_string(), "{\"a\": 1, \"self\": {...}}");
+	assert!(map == map.clone());
+
+	// two different values that contain themselves are compared until they come back around
+	let other = Object::list(vec![Int(1)]);
+	if let List(v) = &other {
+		v.borrow_mut().push(other.clone());
+	}
+	assert!(list == other);
+	assert_eq!(list.partial_cmp(&other), None);
+	assert!(Object::list(vec![Int(0), list.clone()]) < Object::list(vec![Int(1), other]));
+}
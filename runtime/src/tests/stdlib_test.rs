@@ -0,0 +1,555 @@
+use super::run;
+
+#[test]
+fn string_functions() {
+	let src = r#"
+  println(len("héllo"), len([1, 2]))
+  println(substr("hello", 1, 3), substr("hello", 2))
+  println(join(split("a,b,c", ","), "-"), join(split("  a b  "), "+"))
+  println(trim("  x  "), upper("abc"), lower("ABC"))
+  println(contains("hello", "ell"), starts_with("hello", "he"), starts_with("hello", "lo"))
+  println(replace("a-b-c", "-", "+"))
+  println(find("héllo", "llo"), find("hello", "z"))
+  println(chars("abc"))
+  "#;
+	assert_eq!(
+		run(src).unwrap(),
+		"52\nellllo\na-b-ca+b\nxABCabc\ntruetruefalse\na+b+c\n2-1\n[\"a\", \"b\", \"c\"]\n"
+	);
+}
+
+#[test]
+fn string_function_errors() {
+	assert!(run(r#"len(1)"#).is_err());
+	assert!(run(r#"upper(1)"#).is_err());
+	assert!(run(r#"split("abc", "")"#).is_err());
+	assert!(run(r#"substr("abc", 4)"#).is_err());
+	assert!(run(r#"contains("abc")"#).is_err());
+}
+
+#[test]
+fn iterator_functions() {
+	let src = r#"
+  fn double(x) {
+    return x * 2
+  }
+  fn is_even(x) {
+    return x % 2 == 0
+  }
+  fn add(a, b) {
+    return a + b
+  }
+  println(list(map([1, 2, 3], double)))
+  println(list(filter(range(10), is_even)))
+  println(reduce(range(5), add), " ", reduce([], add, 100), " ", reduce("abc", add))
+  println(list(zip("ab", range(5))), list(enumerate(["x", "y"])))
+  println(list(take(map(range(1000000000), double), 3)))
+  for pair in enumerate(take("hello", 2)) {
+    print(pair[0], pair[1])
+  }
+  "#;
+	assert_eq!(
+		run(src).unwrap(),
+		"[2, 4, 6]\n[0, 2, 4, 6, 8]\n10 100 abc\n[[\"a\", 0], [\"b\", 1]][[0, \"x\"], [1, \"y\"]]\n[0, 2, 4]\n0h1e"
+	);
+
+	assert!(run("reduce([], print)").is_err());
+	assert!(run("map([1], 1)").is_err());
+	assert!(run("list(5)").is_err());
+}
+
+#[test]
+fn iterators_are_single_pass_and_lazy() {
+	let src = r#"
+  var calls = 0
+  fn count(x) {
+    calls += 1
+    return x
+  }
+  var it = map(range(5), count)
+  println(calls)
+  println(list(take(it, 2)), list(it), list(it), calls)
+  "#;
+	assert_eq!(run(src).unwrap(), "0\n[0, 1][2, 3, 4][]5\n");
+}
+
+#[test]
+fn function_iterables() {
+	let src = r#"
+  var n = 0
+  fn countdown() {
+    while n < 3 {
+      n += 1
+      return n
+    }
+  }
+  for v in countdown {
+    print(v)
+  }
+  "#;
+	assert_eq!(run(src).unwrap(), "123");
+}
+
+#[test]
+fn host_iterators() {
+	use crate::{
+		interpreter::Interpreter,
+		object::{Arity, Object, Object::*},
+	};
+
+	let mut i = Interpreter::default();
+	i.define_native("squares", Arity::Exact(0), |_, _| {
+		Ok(Object::from_iter((1..=3).map(|v| Int(v * v))))
+	});
+	i.eval("for v in squares() { print(v) }").unwrap();
+	assert_eq!(i.stdout, "149");
+}
+
+#[test]
+fn native_arity() {
+	assert_eq!(run("println()\nprint()").unwrap(), "\n");
+	assert_eq!(
+		run("len()").unwrap_err(),
+		"(Ln 1, Col 1) `len` takes 1 argument, but 0 were given"
+	);
+	assert_eq!(
+		run("range(1, 2, 3, 4)").unwrap_err(),
+		"(Ln 1, Col 1) `range` takes 1 to 3 arguments, but 4 were given"
+	);
+	// methods don't count the receiver
+	assert_eq!(
+		run("\"abc\".contains()").unwrap_err(),
+		"(Ln 1, Col 1) `contains` takes 1 argument, but 0 were given"
+	);
+	assert_eq!(
+		run("[1].len(1)").unwrap_err(),
+		"(Ln 1, Col 1) `len` takes 0 arguments, but 1 was given"
+	);
+	assert_eq!(
+		run("len(1, 2)").unwrap_err(),
+		"(Ln 1, Col 1) `len` takes 1 argument, but 2 were given"
+	);
+
+	use crate::{interpreter::Interpreter, object::Arity};
+	let mut i = Interpreter::default();
+	i.define_native("first", Arity::Variadic(1), |args, _| Ok(args[0].clone()));
+	assert_eq!(i.eval("first(1, 2, 3)").unwrap(), Some("1".to_string()));
+	assert_eq!(
+		i.eval("first()").unwrap_err().to_string(),
+		"(Ln 1, Col 1) `first` takes at least 1 argument, but 0 were given"
+	);
+}
+
+#[test]
+fn math_functions() {
+	let src = r#"
+  println(abs(-3), " ", abs(-2.5), " ", floor(2.7), " ", ceil(2.1), " ", round(-2.5), " ", round(4))
+  println(sqrt(16), " ", pow(2, 10), " ", pow(2, -1), " ", pow(4, 0.5))
+  println(min(3, 1.5, 2), " ", max(3, 1.5, 2), " ", clamp(15, 0, 10), " ", clamp(-1, 0, 10))
+  println(sin(0), " ", cos(0), " ", atan2(1, 1) * 4 == pi, " ", exp(0), " ", log(e), " ", log(8, 2))
+  println(is_nan(sqrt(-1)), " ", is_finite(1 / 0.0), " ", is_finite(1))
+  println(parse_number("42") + 1, " ", parse_number(" 2.5 "), " ", to_fixed(pi, 2), " ", to_fixed(2, 0))
+  "#;
+	assert_eq!(
+		run(src).unwrap(),
+		"3 2.5 2 3 -3 4
+4.0 1024 0.5 2.0
+1.5 3 10 0
+0.0 1.0 true 1.0 1.0 3.0
+true false true
+43 2.5 3.14 2
+"
+	);
+}
+
+#[test]
+fn math_function_errors() {
+	let cases = [
+		(
+			"abs(\"1\")",
+			"Expected argument 1 to be a number, instead got string",
+		),
+		("floor(1 / 0.0)", "Can't convert inf to an int"),
+		("pow(2, 64)", "Integer overflow in pow(2, 64)"),
+		(
+			"clamp(1, 10, 0)",
+			"The low bound 10 is greater than the high bound 0",
+		),
+		("max()", "`max` takes at least 1 argument, but 0 were given"),
+		(
+			"min(1, \"2\")",
+			"Expected argument 2 to be a number, instead got string",
+		),
+		(
+			"to_fixed(1.5, 100000)",
+			"Can't show more than 100 digits, instead got 100000",
+		),
+	];
+	for (src, message) in cases {
+		assert_eq!(run(src).unwrap_err(), format!("(Ln 1, Col 1) {message}"));
+	}
+	assert_eq!(
+		run("parse_number(\"1x\")").unwrap_err(),
+		"(Ln 1, Col 1) Can't parse \"1x\" as a number"
+	);
+
+	// the constants can be shadowed but not changed
+	assert_eq!(
+		run("pi = 3").unwrap_err(),
+		"(Ln 1, Col 1) Can't assign to constant `pi`"
+	);
+	assert_eq!(
+		run("fn f() {\n  e = 1\n}\nf()").unwrap_err(),
+		"(Ln 2, Col 2) Can't assign to constant `e`"
+	);
+	assert_eq!(
+		run("var e = 1\nprintln(e, \" \", pi > 3)").unwrap(),
+		"1 true\n"
+	);
+}
+
+#[test]
+fn seeded_random() {
+	let src = r#"
+  var xs = [1, 2, 3, 4, 5]
+  shuffle(xs)
+  println(random(), " ", random_int(1, 6), " ", choice(["a", "b", "c"]), " ", xs)
+  "#;
+	// the same seed gives the same numbers, whether it's set by the host or the script
+	let seeded = format!("seed(7)\n{src}");
+	let first = run(&seeded).unwrap();
+	assert_eq!(first, "0.45244189501146836 4 b [5, 2, 4, 1, 3]\n");
+	assert_eq!(run(&seeded).unwrap(), first);
+
+	use crate::{interpreter::Interpreter, lexer::Lexer, parser::Parser};
+	let mut i = Interpreter::new(
+		Parser::new(Lexer::new(src).parse().unwrap())
+			.parse()
+			.unwrap(),
+	);
+	i.seed(7);
+	i.run().unwrap();
+	assert_eq!(i.stdout, first);
+
+	// ints cover both ends of the range, and floats stay below 1
+	let src = r#"
+  seed(1)
+  var low = 0
+  var high = 0
+  var top = 0
+  for _ in range(1000) {
+    var v = random_int(-2, 2)
+    low = min(low, v)
+    high = max(high, v)
+    top = max(top, random())
+  }
+  var xs = list(range(5))
+  shuffle(xs)
+  println(low, " ", high, " ", top < 1, " ", len(xs), " ", reduce(xs, max), " ", random_int(3, 3))
+  "#;
+	assert_eq!(run(src).unwrap(), "-2 2 true 5 4 3\n");
+
+	let errors = [
+		("choice([])", "Can't choose from an empty list"),
+		(
+			"random_int(2, 1)",
+			"The low bound 2 is greater than the high bound 1",
+		),
+		(
+			"shuffle(\"abc\")",
+			"Expected argument 1 to be a list, instead got string",
+		),
+		("random(1)", "`random` takes 0 arguments, but 1 was given"),
+	];
+	for (src, message) in errors {
+		assert_eq!(run(src).unwrap_err(), format!("(Ln 1, Col 1) {message}"));
+	}
+}
+
+#[test]
+fn input_functions() {
+	use crate::interpreter::{Input, Interpreter};
+
+	let mut i = Interpreter::default();
+	i.input = Input::Buffer("ann\r\n2\nlast line\nrest\n".to_string());
+	i.eval(
+		r#"
+  var name = input("name? ")
+  var n = parse_number(read_line())
+  println("hi ", name, " ", n + 1)
+  println(read_line(), "|", read_all(), "|", read_line())
+  "#,
+	)
+	.unwrap();
+	assert_eq!(i.stdout, "name? hi ann 3\nlast line|rest\n|nil\n");
+
+	i.input = Input::Disabled;
+	assert_eq!(
+		i.eval("read_line()").unwrap_err().to_string(),
+		"(Ln 1, Col 1) Reading input is disabled"
+	);
+	assert!(i.eval("read_all()").is_err());
+	assert!(i.eval("input(1)").is_err());
+}
+
+#[test]
+fn file_functions() {
+	use crate::interpreter::Interpreter;
+
+	let dir = std::env::temp_dir().join("simple_file_functions");
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("data")).unwrap();
+	std::fs::write(dir.join("data/in.txt"), "hello").unwrap();
+	std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+	let dir = dir.canonicalize().unwrap();
+	let data = dir.join("data");
+
+	// nothing is allowed by default
+	let mut i = Interpreter::default();
+	let read = format!("read_file(\"{}/in.txt\")", data.display());
+	assert_eq!(
+		i.eval(&read).unwrap_err().to_string(),
+		format!(
+			"(Ln 1, Col 1) Reading \"{}/in.txt\" isn't allowed",
+			data.display()
+		)
+	);
+
+	// read only
+	i.fs.allow_read(&data).unwrap();
+	assert_eq!(i.eval(&read).unwrap(), Some("\"hello\"".to_string()));
+	let src = format!("write_file(\"{}/out.txt\", \"x\")", data.display());
+	assert!(i.eval(&src).is_err());
+	let src = format!("read_file(\"{}/data/../secret.txt\")", dir.display());
+	assert!(i.eval(&src).is_err());
+
+	i.fs.allow_write(&data).unwrap();
+	let src = format!(
+		r#"
+  var dir = "{}"
+  write_file(dir + "/out.txt", "a")
+  append_file(dir + "/out.txt", "b")
+  append_file(dir + "/new.txt", "c")
+  println(read_file(dir + "/out.txt"), " ", list_dir(dir), " ", exists(dir + "/new.txt"))
+  remove_file(dir + "/new.txt")
+  println(exists(dir + "/new.txt"))
+  try {{
+    read_file(dir + "/missing.txt")
+  }} catch e {{
+    println("caught")
+  }}
+  "#,
+		data.display()
+	);
+	i.eval(&src).unwrap();
+	assert_eq!(
+		i.stdout,
+		"ab [\"in.txt\", \"new.txt\", \"out.txt\"] true\nfalse\ncaught\n"
+	);
+
+	// `..` can't get out of an allowed directory, even through a directory that's missing
+	for path in ["../secret.txt", "missing/../../secret.txt"] {
+		let src = format!("write_file(\"{}/{path}\", \"x\")", data.display());
+		assert!(i.eval(&src).is_err(), "{path}");
+	}
+	assert_eq!(
+		std::fs::read_to_string(dir.join("secret.txt")).unwrap(),
+		"secret"
+	);
+	assert!(i.eval("read_file(1)").is_err());
+}
+
+#[test]
+fn process_functions() {
+	use crate::{error::Error, interpreter::Interpreter, lexer::Lexer, parser::Parser};
+
+	let mut i = Interpreter::default();
+	i.args = vec!["a".to_string(), "b c".to_string()];
+	assert_eq!(
+		i.eval("args()").unwrap(),
+		Some("[\"a\", \"b c\"]".to_string())
+	);
+
+	assert!(i.eval("env(\"PATH\")").is_err());
+	i.allow_env = true;
+	assert!(i.eval("env(\"PATH\")").unwrap().is_some());
+	assert_eq!(i.eval("env(\"SIMPLE_SURELY_UNSET\")").unwrap(), None);
+
+	// exit goes through try and out of functions, but finally still runs
+	let src = r#"
+  fn stop() {
+    try {
+      exit(3)
+    } catch e {
+      println("caught")
+    } finally {
+      println("finally")
+    }
+  }
+  stop()
+  println("after")
+  "#;
+	let mut i = Interpreter::new(
+		Parser::new(Lexer::new(src).parse().unwrap())
+			.parse()
+			.unwrap(),
+	);
+	assert!(matches!(i.run(), Err(Error::Exit(3))));
+	assert_eq!(i.stdout, "finally\n");
+
+	let mut i = Interpreter::default();
+	assert!(matches!(i.eval("exit()"), Err(Error::Exit(0))));
+	// the REPL keeps going after other errors, which shouldn't look like an exit
+	assert!(matches!(i.eval("throw 1"), Err(Error::Throw { .. })));
+	assert!(run("exit(\"1\")").is_err());
+	assert!(run("exit(1 << 40)").is_err());
+}
+
+#[test]
+fn time_functions() {
+	use crate::interpreter::{Clock, Interpreter};
+
+	let mut i = Interpreter::default();
+	// 2024-02-29T23:59:58Z
+	i.clock = Clock::fixed(1709251198.5);
+	i.eval(
+		r#"
+  var start = monotonic()
+  println(now(), " ", format_time(now()))
+  sleep(1500)
+  println(monotonic() - start, " ", format_time(now(), "%d/%m/%Y %H:%M:%S %%"))
+  println(parse_time("2024-02-29T23:59:58Z"), " ", parse_time("1969-12-31 23:00", "%Y-%m-%d %H:%M"))
+  println(format_time(-1), " ", format_time(0, "%Y"), " ", parse_time("0001-01-01T00:00:00Z"))
+  "#,
+	)
+	.unwrap();
+	assert_eq!(
+		i.stdout,
+		"1709251198.5 2024-02-29T23:59:58Z
+1.5 01/03/2024 00:00:00 %
+1709251198 -3600
+1969-12-31T23:59:59Z 1970 -62135596800
+"
+	);
+
+	for src in [
+		"parse_time(\"2023-02-29T00:00:00Z\")",
+		"parse_time(\"2024-13-01T00:00:00Z\")",
+		"parse_time(\"2024-01-01T24:00:00Z\")",
+		"parse_time(\"2024-01-01\")",
+		"parse_time(\"2024-01-01T00:00:00Zx\")",
+		"format_time(0, \"%q\")",
+		"format_time(0, \"%\")",
+		"sleep(-1)",
+		"sleep(pow(10.0, 300))",
+	] {
+		assert!(i.eval(src).is_err(), "{src}");
+	}
+
+	// the system clock
+	let mut i = Interpreter::default();
+	i.eval("var start = monotonic()\nsleep(5)\nvar t = now()")
+		.unwrap();
+	assert_eq!(
+		i.eval("monotonic() - start >= 0.005").unwrap(),
+		Some("true".to_string())
+	);
+	assert_eq!(i.eval("t > 1700000000").unwrap(), Some("true".to_string()));
+}
+
+#[test]
+fn json() {
+	use crate::interpreter::{Input, Interpreter};
+
+	// scripts can't write escapes in strings, so the JSON is read from the input instead
+	let run_with = |src: &str, input: &str| {
+		let mut i = Interpreter::default();
+		i.input = Input::Buffer(input.to_string());
+		i.eval(src).map(|_| i.stdout)
+	};
+
+	let input = r#"{"name": "a\"b\u00e9\ud83d\ude00", "n": [1, -2.5, 1e2, 99999999999999999999],
+  "ok": true, "none": null, "empty": {}}
+"a\tb""#;
+	let src = r#"
+  var data = json_parse(read_line() + read_line())
+  println(data)
+  println(data["name"], " ", data["n"][2], " ", json_parse(" [ ] "))
+  println(json_stringify(data))
+  struct Point { x, y }
+  var m = new_map()
+  m["points"] = [Point(1, 2)]
+  m["tab"] = json_parse(read_line())
+  println(json_stringify(m, 2))
+  println(json_stringify([[], new_map()], 2), " ", json_parse(json_stringify(data)) == data)
+  "#;
+	assert_eq!(
+		run_with(src, input).unwrap(),
+		r#"{"name": "a\"bé😀", "n": [1, -2.5, 100.0, 100000000000000000000.0], "ok": true, "none": nil, "empty": {}}
+a"bé😀 100.0 []
+{"name":"a\"bé😀","n":[1,-2.5,100.0,100000000000000000000.0],"ok":true,"none":null,"empty":{}}
+{
+  "points": [
+    {
+      "x": 1,
+      "y": 2
+    }
+  ],
+  "tab": "a\tb"
+}
+[
+  [],
+  {}
+] true
+"#
+	);
+
+	// errors point at where the JSON stops being valid
+	let parse_error = |json: &str| {
+		run_with("json_parse(read_all())", json)
+			.unwrap_err()
+			.to_string()
+	};
+	assert_eq!(
+		parse_error("{\"a\": 1,\n  \"b\" 2}"),
+		"(Ln 1, Col 1) Invalid JSON at line 2, column 7: Expected ':', instead got '2'"
+	);
+	assert_eq!(
+		parse_error("[1, 2"),
+		"(Ln 1, Col 1) Invalid JSON at line 1, column 6: Expected ',' or ']'"
+	);
+	assert_eq!(
+		parse_error("[1] x"),
+		"(Ln 1, Col 1) Invalid JSON at line 1, column 5: Unexpected 'x' after the value"
+	);
+	for json in [
+		"",
+		"01",
+		"1.",
+		"-",
+		"tru",
+		"{a: 1}",
+		"[1,]",
+		"\"\\x\"",
+		"\"a",
+		"\"\\ud83d\"",
+	] {
+		assert!(parse_error(json).contains("Invalid JSON"), "{json}");
+	}
+	assert!(parse_error(&"[".repeat(1000)).contains("Nested more than 512"));
+
+	assert_eq!(
+		run("json_stringify([print])").unwrap_err(),
+		"(Ln 1, Col 1) Can't convert function to JSON"
+	);
+	assert_eq!(
+		run("var xs = [1]\nxs[0] = xs\njson_stringify(xs)").unwrap_err(),
+		"(Ln 3, Col 0) Can't convert a value that contains itself to JSON"
+	);
+	assert!(run("json_stringify(1 / 0.0)").is_err());
+	assert_eq!(
+		run("json_stringify([1], 9223372036854775807)").unwrap_err(),
+		"(Ln 1, Col 1) Can't indent by more than 10 spaces, instead got 9223372036854775807"
+	);
+	assert!(run("json_stringify([1], 10)").is_ok());
+}
@@ -10,21 +10,45 @@ pub enum TokenKind {
 	Float,
 	True,
 	False,
+	Nil,
 
 	// Keywords
 	Return,
 	Var,
+	Const,
 	Fn,
 	While,
+	For,
+	In,
+	Struct,
+	Impl,
+	Throw,
+	Try,
+	Catch,
+	Finally,
+	Import,
 
 	//// All operators
 	Assign,
+	PlusAssign,
+	MinusAssign,
+	StarAssign,
+	SlashAssign,
 
 	// Math Ops
 	Plus,
 	Minus,
 	Slash,
 	Star,
+	Percent,
+	StarStar,
+
+	// Bitwise Ops
+	Ampersand,
+	Pipe,
+	Caret,
+	ShiftLeft,
+	ShiftRight,
 
 	// Boolean Ops
 	Equals,
@@ -35,16 +59,28 @@ pub enum TokenKind {
 	GreaterEquals,
 	Bang,
 
+	// Nil Ops
+	Coalesce,
+	QuestionDot,
+	/// postfix `?`, which returns errors early
+	Question,
+
 	// groupings
 	Lparen,
 	Rparen,
 	Lbrace,
 	Rbrace,
+	Lbracket,
+	Rbracket,
 
 	// seperators
 	Comma,
 	Semicolon,
+	Colon,
+	Arrow,
 	Dot,
+	/// `...` before a rest parameter
+	Ellipsis,
 
 	EOF,
 }
@@ -53,10 +89,21 @@ pub fn keywords() -> HashMap<String, TokenKind> {
 	use TokenKind::*;
 	[
 		("var", Var),
+		("const", Const),
 		("fn", Fn),
 		("while", While),
+		("for", For),
+		("in", In),
+		("struct", Struct),
+		("impl", Impl),
+		("throw", Throw),
+		("try", Try),
+		("catch", Catch),
+		("finally", Finally),
+		("import", Import),
 		("true", True),
 		("false", False),
+		("nil", Nil),
 		("return", Return),
 	]
 	.into_iter()